@@ -72,12 +72,26 @@ process_qml_output() {
             json="${line#*SAVE_EFFECTS:}"
             echo "$json" > "${EFFECTS_STATE_DIR}/effects_config.json"
             echo "Saved effects config" >> "$LOG_FILE"
-            # Send SIGHUP to reload config, or restart if needed
-            if pkill -HUP -f flick-effects 2>/dev/null; then
-                echo "Sent reload signal to flick-effects" >> "$LOG_FILE"
+            # Ask the overlay to reload over D-Bus, or start it if it is
+            # not running (flick-effectsctl exits with 3 for that). Without
+            # the ctl (installed only when cargo was around), fall back to
+            # SIGHUP, which the overlay also reloads on.
+            ctl="${HOME}/.local/bin/flick-effectsctl"
+            if [ -x "$ctl" ]; then
+                "$ctl" reload 2>>"$LOG_FILE"
+                ctl_status=$?
+            elif pkill -HUP -x flick-effects 2>/dev/null; then
+                ctl_status=0
             else
+                ctl_status=3
+            fi
+            if [ "$ctl_status" -eq 0 ]; then
+                echo "Reloaded flick-effects" >> "$LOG_FILE"
+            elif [ "$ctl_status" -eq 3 ]; then
                 "${HOME}/.local/bin/flick-effects" &
                 echo "Started flick-effects" >> "$LOG_FILE"
+            else
+                echo "Could not reload flick-effects (status $ctl_status)" >> "$LOG_FILE"
             fi
         elif [[ "$line" == *"SAVE_THEME:"* ]]; then
             json="${line#*SAVE_THEME:}"
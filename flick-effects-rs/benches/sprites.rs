@@ -0,0 +1,68 @@
+//! Per-particle arc fills against sprite-cache blits on an offscreen
+//! phone-sized surface: `cargo bench --bench sprites`
+
+use flick_effects::sprites::SpriteCache;
+use gtk4::cairo::{Context, Format, ImageSurface, Operator};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
+use std::time::Instant;
+
+const WIDTH: i32 = 540;
+const HEIGHT: i32 = 1170;
+const PARTICLES: usize = 2000;
+const FRAMES: usize = 100;
+
+/// x, y, radius, color, alpha
+type BenchParticle = (f64, f64, f64, (f64, f64, f64), f64);
+
+fn clear(cr: &Context) {
+    cr.set_operator(Operator::Clear);
+    cr.paint().ok();
+    cr.set_operator(Operator::Over);
+}
+
+/// Milliseconds per frame of drawing every particle with `draw`
+fn time_frames(cr: &Context, surface: &ImageSurface, particles: &[BenchParticle], mut draw: impl FnMut(&BenchParticle)) -> f64 {
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        clear(cr);
+        particles.iter().for_each(&mut draw);
+    }
+    surface.flush();
+    start.elapsed().as_secs_f64() * 1000.0 / FRAMES as f64
+}
+
+fn main() {
+    let surface = ImageSurface::create(Format::ARgb32, WIDTH, HEIGHT).expect("Failed to create benchmark surface");
+    let cr = Context::new(&surface).expect("Failed to create benchmark context");
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let particles: Vec<BenchParticle> = (0..PARTICLES)
+        .map(|_| {
+            let heat = rng.gen::<f64>();
+            (
+                rng.gen::<f64>() * WIDTH as f64,
+                rng.gen::<f64>() * HEIGHT as f64,
+                rng.gen::<f64>() * 15.0 + 1.0,
+                (1.0, 0.3 + heat * 0.6, heat * 0.5),
+                rng.gen::<f64>(),
+            )
+        })
+        .collect();
+
+    let arc_ms = time_frames(&cr, &surface, &particles, |&(x, y, radius, (r, g, b), alpha)| {
+        cr.set_source_rgba(r, g, b, alpha);
+        cr.arc(x, y, radius, 0.0, 2.0 * PI);
+        cr.fill().ok();
+    });
+
+    let mut cache = SpriteCache::new();
+    let sprite_ms = time_frames(&cr, &surface, &particles, |&(x, y, radius, color, alpha)| {
+        cache.draw(&cr, x, y, radius, color, alpha);
+    });
+
+    println!("{} particles, {} frames", PARTICLES, FRAMES);
+    println!("  arc + fill:   {:.2} ms/frame", arc_ms);
+    println!("  sprite cache: {:.2} ms/frame ({} sprites cached)", sprite_ms, cache.cached());
+}
@@ -0,0 +1,207 @@
+//! Stand-in for ModemManager's voice call API, to try the incoming call
+//! ring without a modem. Run it and flick-effects against the same
+//! private bus used as the system bus:
+//!
+//!     dbus-daemon --session --print-address --fork > /tmp/fake-system-bus
+//!     export DBUS_SYSTEM_BUS_ADDRESS=$(head -1 /tmp/fake-system-bus)
+//!     flick-effects &
+//!     cargo run --example mock-modemmanager -- ring 3 answer 2 hangup 1 ring 3 miss
+//!
+//! Arguments alternate between an action (ring, answer, hangup, miss) and
+//! the seconds to wait before the next one.
+
+use glib::variant::ObjectPath;
+use glib::{ToVariant, Variant};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+const MM_PATH: &str = "/org/freedesktop/ModemManager1";
+const MODEM_PATH: &str = "/org/freedesktop/ModemManager1/Modem/0";
+const CALL_INTERFACE: &str = "org.freedesktop.ModemManager1.Call";
+const VOICE_INTERFACE: &str = "org.freedesktop.ModemManager1.Modem.Voice";
+
+const INTROSPECTION: &str = r#"
+<node>
+  <interface name="org.freedesktop.DBus.ObjectManager">
+    <method name="GetManagedObjects">
+      <arg name="objects" type="a{oa{sa{sv}}}" direction="out"/>
+    </method>
+  </interface>
+  <interface name="org.freedesktop.ModemManager1.Modem.Voice">
+    <signal name="CallAdded"><arg name="path" type="o"/></signal>
+    <signal name="CallDeleted"><arg name="path" type="o"/></signal>
+  </interface>
+  <interface name="org.freedesktop.ModemManager1.Call">
+    <signal name="StateChanged">
+      <arg name="old" type="i"/>
+      <arg name="new" type="i"/>
+      <arg name="reason" type="u"/>
+    </signal>
+    <property name="State" type="i" access="read"/>
+    <property name="Direction" type="i" access="read"/>
+    <property name="Number" type="s" access="read"/>
+  </interface>
+</node>
+"#;
+
+// MMCallState
+const RINGING_IN: i32 = 3;
+const ACTIVE: i32 = 4;
+const TERMINATED: i32 = 7;
+// MMCallDirection
+const INCOMING: i32 = 1;
+
+/// The call in progress, if any: object path, registration and state
+struct Call {
+    path: String,
+    registration: gio::RegistrationId,
+    state: Rc<RefCell<i32>>,
+}
+
+struct Mock {
+    connection: gio::DBusConnection,
+    node: gio::DBusNodeInfo,
+    call: RefCell<Option<Call>>,
+    next_call: RefCell<u32>,
+}
+
+impl Mock {
+    fn ring(&self) {
+        if self.call.borrow().is_some() {
+            eprintln!("Already in a call");
+            return;
+        }
+        let id = self.next_call.replace_with(|n| *n + 1);
+        let path = format!("{}/Call/{}", MM_PATH, id);
+        let state = Rc::new(RefCell::new(RINGING_IN));
+
+        let property_state = glib::thread_guard::ThreadGuard::new(state.clone());
+        let property_state = std::sync::Mutex::new(property_state);
+        let registration = self
+            .connection
+            .register_object(
+                &path,
+                &self.node.lookup_interface(CALL_INTERFACE).unwrap(),
+                |_, _, _, _, _, _, invocation| invocation.return_dbus_error("org.freedesktop.DBus.Error.UnknownMethod", "no methods"),
+                move |_, _, _, _, property| match property {
+                    "State" => (*property_state.lock().unwrap().get_ref().borrow()).to_variant(),
+                    "Direction" => INCOMING.to_variant(),
+                    _ => "+15555550100".to_variant(),
+                },
+                |_, _, _, _, _, _| false,
+            )
+            .expect("register call");
+        self.emit(MODEM_PATH, VOICE_INTERFACE, "CallAdded", &(ObjectPath::try_from(path.as_str()).unwrap(),).to_variant());
+        *self.call.borrow_mut() = Some(Call { path, registration, state });
+    }
+
+    fn set_state(&self, new: i32) {
+        let call = self.call.borrow();
+        let Some(call) = call.as_ref() else {
+            eprintln!("No call");
+            return;
+        };
+        let old = call.state.replace(new);
+        self.emit(&call.path, CALL_INTERFACE, "StateChanged", &(old, new, 0u32).to_variant());
+    }
+
+    /// Terminate and remove the call
+    fn end(&self) {
+        self.set_state(TERMINATED);
+        if let Some(call) = self.call.borrow_mut().take() {
+            self.emit(MODEM_PATH, VOICE_INTERFACE, "CallDeleted", &(ObjectPath::try_from(call.path.as_str()).unwrap(),).to_variant());
+            self.connection.unregister_object(call.registration).ok();
+        }
+    }
+
+    fn managed_objects(&self) -> Variant {
+        let mut objects: HashMap<ObjectPath, HashMap<String, HashMap<String, Variant>>> = HashMap::new();
+        if let Some(call) = self.call.borrow().as_ref() {
+            let properties = HashMap::from([
+                ("State".to_string(), call.state.borrow().to_variant()),
+                ("Direction".to_string(), INCOMING.to_variant()),
+            ]);
+            let path = ObjectPath::try_from(call.path.as_str()).unwrap();
+            objects.insert(path, HashMap::from([(CALL_INTERFACE.to_string(), properties)]));
+        }
+        (objects,).to_variant()
+    }
+
+    fn emit(&self, path: &str, interface: &str, signal: &str, body: &Variant) {
+        if let Err(e) = self.connection.emit_signal(None, path, interface, signal, Some(body)) {
+            eprintln!("Cannot emit {}: {}", signal, e);
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        args = "ring 3 answer 2 hangup 1 ring 3 miss".split(' ').map(String::from).collect();
+    }
+
+    let main_loop = glib::MainLoop::new(None, false);
+    let connection = gio::bus_get_sync(gio::BusType::System, gio::Cancellable::NONE).expect("system bus");
+    let mock = Rc::new(Mock {
+        connection: connection.clone(),
+        node: gio::DBusNodeInfo::for_xml(INTROSPECTION).expect("valid introspection"),
+        call: RefCell::new(None),
+        next_call: RefCell::new(1),
+    });
+
+    let objects = std::sync::Mutex::new(glib::thread_guard::ThreadGuard::new(mock.clone()));
+    connection
+        .register_object(
+            MM_PATH,
+            &mock.node.lookup_interface("org.freedesktop.DBus.ObjectManager").unwrap(),
+            move |_, _, _, _, _, _, invocation| {
+                invocation.return_value(Some(&objects.lock().unwrap().get_ref().managed_objects()));
+            },
+            |_, _, _, _, _| false.to_variant(),
+            |_, _, _, _, _, _| false,
+        )
+        .expect("register object manager");
+
+    // DBUS_NAME_FLAG_DO_NOT_QUEUE; 1 means we are the primary owner
+    let reply = connection
+        .call_sync(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "RequestName",
+            Some(&("org.freedesktop.ModemManager1", 4u32).to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            1000,
+            gio::Cancellable::NONE,
+        )
+        .expect("RequestName");
+    if reply.get::<(u32,)>() != Some((1,)) {
+        eprintln!("org.freedesktop.ModemManager1 is already taken");
+        return;
+    }
+    println!("ModemManager mock running");
+    run_script(mock, args, main_loop.clone());
+
+    main_loop.run();
+}
+
+/// Perform the actions in `args`, waiting the given seconds between them
+fn run_script(mock: Rc<Mock>, mut args: Vec<String>, main_loop: glib::MainLoop) {
+    if args.is_empty() {
+        main_loop.quit();
+        return;
+    }
+    let action = args.remove(0);
+    match action.as_str() {
+        "ring" => mock.ring(),
+        "answer" => mock.set_state(ACTIVE),
+        "hangup" | "miss" => mock.end(),
+        _ => eprintln!("Unknown action '{}'", action),
+    }
+    println!("{}", action);
+    let wait = if args.is_empty() { 0.0 } else { args.remove(0).parse().unwrap_or(1.0) };
+    glib::timeout_add_local_once(Duration::from_secs_f64(wait), move || run_script(mock, args, main_loop));
+}
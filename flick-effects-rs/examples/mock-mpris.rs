@@ -0,0 +1,176 @@
+//! Stand-in MPRIS media player, to try the now-playing visualizer and
+//! audio-reactive living pixels without a real player. Run it and
+//! flick-effects on the same private session bus:
+//!
+//!     dbus-run-session -- sh -c 'flick-effects & cargo run --example mock-mpris -- \
+//!         art file:///usr/share/pixmaps/debian-logo.png play 5 pause 3 play 3 quit'
+//!
+//! Arguments alternate between an action (play, pause, stop, quit, or
+//! `art <url>` which takes effect immediately) and the seconds to wait
+//! before the next one. It only reports state; nothing is played.
+
+use glib::{ToVariant, Variant};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.flickmock";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+const INTROSPECTION: &str = r#"
+<node>
+  <interface name="org.mpris.MediaPlayer2">
+    <property name="Identity" type="s" access="read"/>
+  </interface>
+  <interface name="org.mpris.MediaPlayer2.Player">
+    <method name="Play"/>
+    <method name="Pause"/>
+    <method name="PlayPause"/>
+    <method name="Stop"/>
+    <property name="PlaybackStatus" type="s" access="read"/>
+    <property name="Metadata" type="a{sv}" access="read"/>
+  </interface>
+</node>
+"#;
+
+struct Player {
+    connection: gio::DBusConnection,
+    status: RefCell<String>,
+    art_url: RefCell<Option<String>>,
+}
+
+impl Player {
+    fn metadata(&self) -> Variant {
+        let mut metadata: HashMap<String, Variant> = HashMap::from([
+            ("mpris:trackid".to_string(), glib::variant::ObjectPath::try_from("/org/flick/mock/track/1").unwrap().to_variant()),
+            ("xesam:title".to_string(), "Test Tone".to_variant()),
+        ]);
+        if let Some(url) = self.art_url.borrow().as_ref() {
+            metadata.insert("mpris:artUrl".to_string(), url.to_variant());
+        }
+        metadata.to_variant()
+    }
+
+    fn property(&self, name: &str) -> Variant {
+        match name {
+            "PlaybackStatus" => self.status.borrow().to_variant(),
+            "Metadata" => self.metadata(),
+            _ => "Flick mock player".to_variant(),
+        }
+    }
+
+    fn set_status(&self, status: &str) {
+        self.status.replace(status.to_string());
+        self.changed("PlaybackStatus");
+    }
+
+    fn set_art(&self, url: String) {
+        self.art_url.replace(Some(url));
+        self.changed("Metadata");
+    }
+
+    /// Emit PropertiesChanged for one Player property
+    fn changed(&self, name: &str) {
+        let changed = HashMap::from([(name.to_string(), self.property(name))]);
+        let body = (PLAYER_INTERFACE, changed, Vec::<String>::new()).to_variant();
+        if let Err(e) = self.connection.emit_signal(None, MPRIS_PATH, "org.freedesktop.DBus.Properties", "PropertiesChanged", Some(&body)) {
+            eprintln!("Cannot emit PropertiesChanged: {}", e);
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        args = "play 5 pause 3 play 3 stop".split(' ').map(String::from).collect();
+    }
+
+    let main_loop = glib::MainLoop::new(None, false);
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE).expect("session bus");
+    let node = gio::DBusNodeInfo::for_xml(INTROSPECTION).expect("valid introspection");
+    let player = Rc::new(Player {
+        connection: connection.clone(),
+        status: RefCell::new("Stopped".to_string()),
+        art_url: RefCell::new(None),
+    });
+
+    for interface in ["org.mpris.MediaPlayer2", PLAYER_INTERFACE] {
+        let methods = std::sync::Mutex::new(glib::thread_guard::ThreadGuard::new(player.clone()));
+        let properties = std::sync::Mutex::new(glib::thread_guard::ThreadGuard::new(player.clone()));
+        connection
+            .register_object(
+                MPRIS_PATH,
+                &node.lookup_interface(interface).unwrap(),
+                move |_, _, _, _, method, _, invocation| {
+                    let methods = methods.lock().unwrap();
+                    let player = methods.get_ref();
+                    match method {
+                        "Play" => player.set_status("Playing"),
+                        "Pause" => player.set_status("Paused"),
+                        "Stop" => player.set_status("Stopped"),
+                        _ => {
+                            let playing = *player.status.borrow() == "Playing";
+                            player.set_status(if playing { "Paused" } else { "Playing" });
+                        }
+                    }
+                    invocation.return_value(None);
+                },
+                move |_, _, _, _, name| properties.lock().unwrap().get_ref().property(name),
+                |_, _, _, _, _, _| false,
+            )
+            .expect("register player");
+    }
+
+    // DBUS_NAME_FLAG_DO_NOT_QUEUE; 1 means we are the primary owner
+    let reply = connection
+        .call_sync(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "RequestName",
+            Some(&(BUS_NAME, 4u32).to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            1000,
+            gio::Cancellable::NONE,
+        )
+        .expect("RequestName");
+    if reply.get::<(u32,)>() != Some((1,)) {
+        eprintln!("{} is already taken", BUS_NAME);
+        return;
+    }
+    println!("MPRIS mock running as {}", BUS_NAME);
+    run_script(player, args, main_loop.clone());
+
+    main_loop.run();
+}
+
+/// Perform the actions in `args`, waiting the given seconds between them
+fn run_script(player: Rc<Player>, mut args: Vec<String>, main_loop: glib::MainLoop) {
+    if args.is_empty() {
+        return;
+    }
+    let action = args.remove(0);
+    match action.as_str() {
+        "play" => player.set_status("Playing"),
+        "pause" => player.set_status("Paused"),
+        "stop" => player.set_status("Stopped"),
+        "quit" => {
+            main_loop.quit();
+            return;
+        }
+        "art" => {
+            if !args.is_empty() {
+                player.set_art(args.remove(0));
+            }
+            println!("art");
+            return run_script(player, args, main_loop);
+        }
+        _ => eprintln!("Unknown action '{}'", action),
+    }
+    println!("{}", action);
+    let wait = if args.is_empty() { 0.0 } else { args.remove(0).parse().unwrap_or(1.0) };
+    glib::timeout_add_local_once(Duration::from_secs_f64(wait), move || run_script(player, args, main_loop));
+}
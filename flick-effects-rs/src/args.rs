@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use crate::config::Renderer;
+
+/// Command-line options for flick-effects
+pub struct Args {
+    /// argv[0], handed on to GApplication
+    pub program: String,
+    /// RNG seed for reproducible simulations (overrides config)
+    pub seed: Option<u64>,
+    /// Write the touch event stream to this file
+    pub record: Option<PathBuf>,
+    /// Read touch events from this recording instead of the touchscreen
+    pub replay: Option<PathBuf>,
+    /// Drawing backend (overrides config)
+    pub renderer: Option<Renderer>,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut argv = std::env::args();
+        let mut args = Args {
+            program: argv.next().unwrap_or_else(|| "flick-effects".to_string()),
+            seed: None,
+            record: None,
+            replay: None,
+            renderer: None,
+        };
+
+        while let Some(arg) = argv.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    args.seed = argv.next().and_then(|v| v.parse().ok());
+                    if args.seed.is_none() {
+                        eprintln!("--seed expects an unsigned integer");
+                    }
+                }
+                "--record" => args.record = argv.next().map(PathBuf::from),
+                "--replay" => args.replay = argv.next().map(PathBuf::from),
+                "--renderer" => match argv.next().map(|v| v.parse()) {
+                    Some(Ok(renderer)) => args.renderer = Some(renderer),
+                    Some(Err(e)) => eprintln!("--renderer: {}", e),
+                    None => eprintln!("--renderer expects cairo or gl"),
+                },
+                _ => eprintln!("Ignoring unknown argument: {}", arg),
+            }
+        }
+
+        args
+    }
+}
@@ -0,0 +1,316 @@
+use std::cell::Cell;
+use std::f64::consts::PI;
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::thread;
+
+use crate::touch::attach_to_main_loop;
+
+/// Capture format: mono 16-bit at a rate that covers the treble band
+const SAMPLE_RATE: u32 = 22050;
+/// Samples per analysis frame, about 46 ms
+const FRAME: usize = 1024;
+/// Band edges in Hz
+const BASS: (f64, f64) = (20.0, 250.0);
+const MID: (f64, f64) = (250.0, 2000.0);
+const TREBLE: (f64, f64) = (2000.0, 8000.0);
+/// Log-spaced bands from SPECTRUM_LOW up to the top of the treble band
+pub const SPECTRUM_BANDS: usize = 16;
+const SPECTRUM_LOW: f64 = 40.0;
+/// Levels are mapped from -60..0 dBFS onto 0..1
+const FLOOR_DB: f64 = -60.0;
+/// A beat is bass energy this far above its average over the last second
+const BEAT_RATIO: f64 = 1.5;
+const BEAT_MIN_GAP: f64 = 0.25;
+
+/// Loudness of the audio playing right now, each from 0 to 1
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AudioLevels {
+    pub rms: f64,
+    pub bass: f64,
+    pub mid: f64,
+    pub treble: f64,
+    /// Bass to treble, for visualizers
+    pub spectrum: [f64; SPECTRUM_BANDS],
+    /// Set on the frame a beat starts
+    pub beat: bool,
+}
+
+/// Captures the default sink's monitor with `parec`, which PulseAudio and
+/// PipeWire (through pipewire-pulse) both provide, and analyzes it on a
+/// worker thread. Capture stops when this is dropped.
+pub struct AudioAnalyzer {
+    child: Child,
+    /// Cleared on drop so frames still queued for the main loop, and the
+    /// end of capture that killing parec causes, are not reported
+    running: Rc<Cell<bool>>,
+    _handle: thread::JoinHandle<()>,
+}
+
+impl AudioAnalyzer {
+    /// Call `on_levels` on the main loop for each analyzed frame, and
+    /// `on_end` if parec exits by itself (no sound server, or it went away)
+    pub fn new<F, E>(mut on_levels: F, on_end: E) -> std::io::Result<Self>
+    where
+        F: FnMut(AudioLevels) + 'static,
+        E: FnOnce() + 'static,
+    {
+        let mut child = record_monitor()?;
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let running = Rc::new(Cell::new(true));
+        let reporting = running.clone();
+        let mut on_end = Some(on_end);
+        // None marks the end of capture
+        let sender = attach_to_main_loop(move |levels: Option<AudioLevels>| {
+            if !reporting.get() {
+                return;
+            }
+            match levels {
+                Some(levels) => on_levels(levels),
+                None => {
+                    if let Some(on_end) = on_end.take() {
+                        on_end();
+                    }
+                }
+            }
+        });
+
+        let handle = thread::spawn(move || {
+            let mut analysis = Analysis::new();
+            let mut bytes = vec![0u8; FRAME * 2];
+            // Ends when parec exits or is killed on drop
+            while stdout.read_exact(&mut bytes).is_ok() {
+                sender.send(Some(analysis.analyze(&samples(&bytes))));
+            }
+            sender.send(None);
+        });
+
+        println!("Listening to the default sink's monitor");
+        Ok(Self { child, running, _handle: handle })
+    }
+}
+
+impl Drop for AudioAnalyzer {
+    fn drop(&mut self) {
+        self.running.set(false);
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+fn record_monitor() -> std::io::Result<Child> {
+    Command::new("parec")
+        .args(["--device=@DEFAULT_MONITOR@", "--raw", "--format=s16le", "--channels=1", "--latency-msec=20"])
+        .arg(format!("--rate={}", SAMPLE_RATE))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+/// Little-endian 16-bit PCM to -1..1
+fn samples(bytes: &[u8]) -> Vec<f64> {
+    bytes
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]) as f64 / 32768.0)
+        .collect()
+}
+
+// ============ ANALYSIS ============
+
+/// Per-stream analysis state: the window, FFT buffers and recent bass
+/// energy for beat detection
+struct Analysis {
+    window: Vec<f64>,
+    re: Vec<f64>,
+    im: Vec<f64>,
+    bass_history: Vec<f64>,
+    since_beat: f64,
+}
+
+impl Analysis {
+    fn new() -> Self {
+        let window = (0..FRAME)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / (FRAME - 1) as f64).cos())
+            .collect();
+        Self {
+            window,
+            re: vec![0.0; FRAME],
+            im: vec![0.0; FRAME],
+            bass_history: Vec::new(),
+            since_beat: 0.0,
+        }
+    }
+
+    fn analyze(&mut self, samples: &[f64]) -> AudioLevels {
+        let rms = (samples.iter().map(|s| s * s).sum::<f64>() / samples.len().max(1) as f64).sqrt();
+
+        for i in 0..FRAME {
+            self.re[i] = samples.get(i).copied().unwrap_or(0.0) * self.window[i];
+            self.im[i] = 0.0;
+        }
+        fft(&mut self.re, &mut self.im);
+
+        let bass_power = self.band_power(BASS);
+        let bass = level(bass_power);
+
+        // Beats: bass well above its recent average, not too close together
+        let frame_seconds = FRAME as f64 / SAMPLE_RATE as f64;
+        let frames_per_second = (1.0 / frame_seconds) as usize;
+        let average = self.bass_history.iter().sum::<f64>() / self.bass_history.len().max(1) as f64;
+        self.since_beat += frame_seconds;
+        let beat = self.bass_history.len() >= frames_per_second / 2
+            && bass_power > average * BEAT_RATIO
+            && bass > 0.3
+            && self.since_beat >= BEAT_MIN_GAP;
+        if beat {
+            self.since_beat = 0.0;
+        }
+        self.bass_history.push(bass_power);
+        if self.bass_history.len() > frames_per_second {
+            self.bass_history.remove(0);
+        }
+
+        let mut spectrum = [0.0; SPECTRUM_BANDS];
+        let step = (TREBLE.1 / SPECTRUM_LOW).powf(1.0 / SPECTRUM_BANDS as f64);
+        for (i, band) in spectrum.iter_mut().enumerate() {
+            let low = SPECTRUM_LOW * step.powi(i as i32);
+            *band = level(self.band_peak((low, low * step)));
+        }
+
+        AudioLevels {
+            rms: level(rms * rms),
+            bass,
+            mid: level(self.band_power(MID)),
+            treble: level(self.band_power(TREBLE)),
+            spectrum,
+            beat,
+        }
+    }
+
+    /// Mean power of the bins between `low` and `high` Hz, where a
+    /// full-scale sine in a bin is 0.5
+    fn band_power(&self, band: (f64, f64)) -> f64 {
+        let bins = self.bin_powers(band);
+        bins.clone().sum::<f64>() / bins.len().max(1) as f64
+    }
+
+    /// Power of the strongest bin between `low` and `high` Hz, where a
+    /// full-scale sine is 0.5; for the narrow spectrum bands, which can
+    /// hold as little as one bin
+    fn band_peak(&self, band: (f64, f64)) -> f64 {
+        // A sine spreads over a few bins; the strongest one stands for the band
+        self.bin_powers(band).fold(0.0, f64::max)
+    }
+
+    /// Power of each bin between `low` and `high` Hz
+    fn bin_powers(&self, (low, high): (f64, f64)) -> impl ExactSizeIterator<Item = f64> + Clone + '_ {
+        let bin_hz = SAMPLE_RATE as f64 / FRAME as f64;
+        let first = ((low / bin_hz).ceil() as usize).max(1);
+        let last = ((high / bin_hz).floor() as usize).min(FRAME / 2);
+        // The Hann window halves a sine's amplitude at its bin
+        let scale = 4.0 / FRAME as f64;
+        (first..last + 1).map(move |i| {
+            let amplitude = (self.re[i] * self.re[i] + self.im[i] * self.im[i]).sqrt() * scale;
+            amplitude * amplitude / 2.0
+        })
+    }
+}
+
+/// Power relative to a full-scale sine, as 0..1 over FLOOR_DB..0 dB
+fn level(power: f64) -> f64 {
+    let db = 10.0 * (power * 2.0).max(1e-12).log10();
+    ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+}
+
+/// In-place radix-2 FFT; the length must be a power of two
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        let (w_re, w_im) = (angle.cos(), angle.sin());
+        for start in (0..n).step_by(len) {
+            let (mut u_re, mut u_im) = (1.0, 0.0);
+            for k in 0..len / 2 {
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * u_re - im[b] * u_im;
+                let t_im = re[b] * u_im + im[b] * u_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                let next = u_re * w_re - u_im * w_im;
+                u_im = u_re * w_im + u_im * w_re;
+                u_re = next;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One frame of a sine at `hz`
+    fn tone(hz: f64, amplitude: f64) -> Vec<f64> {
+        (0..FRAME)
+            .map(|i| amplitude * (2.0 * PI * hz * i as f64 / SAMPLE_RATE as f64).sin())
+            .collect()
+    }
+
+    #[test]
+    fn low_tone_is_bass() {
+        let levels = Analysis::new().analyze(&tone(100.0, 0.5));
+        assert!(levels.bass > 0.4, "{:?}", levels);
+        assert!(levels.mid < 0.2 && levels.treble < 0.2, "{:?}", levels);
+    }
+
+    #[test]
+    fn high_tone_is_treble() {
+        let levels = Analysis::new().analyze(&tone(5000.0, 0.5));
+        assert!(levels.treble > 0.4, "{:?}", levels);
+        assert!(levels.bass < 0.2 && levels.mid < 0.2, "{:?}", levels);
+    }
+
+    #[test]
+    fn tone_peaks_in_its_spectrum_band() {
+        let step = (TREBLE.1 / SPECTRUM_LOW).powf(1.0 / SPECTRUM_BANDS as f64);
+        for hz in [100.0, 5000.0] {
+            let spectrum = Analysis::new().analyze(&tone(hz, 0.5)).spectrum;
+            let loudest = (0..SPECTRUM_BANDS)
+                .max_by(|&a, &b| spectrum[a].total_cmp(&spectrum[b]))
+                .unwrap();
+            let expected = ((hz / SPECTRUM_LOW).ln() / step.ln()) as usize;
+            assert_eq!(loudest, expected, "{} Hz: {:?}", hz, spectrum);
+        }
+    }
+
+    #[test]
+    fn beat_on_loud_bass_after_quiet() {
+        let mut analysis = Analysis::new();
+        let quiet = tone(100.0, 0.05);
+        for _ in 0..SAMPLE_RATE as usize / FRAME {
+            assert!(!analysis.analyze(&quiet).beat, "steady bass is not a beat");
+        }
+        let loud = tone(100.0, 0.8);
+        assert!(analysis.analyze(&loud).beat);
+        // Too soon after the last one
+        assert!(!analysis.analyze(&loud).beat);
+    }
+}
@@ -0,0 +1,188 @@
+//! Command-line client for the org.flick.Effects D-Bus interface
+//!
+//!     flick-effectsctl enable fire
+//!     flick-effectsctl disable living-pixels
+//!     flick-effectsctl trigger fire 200 400 --duration 1.5
+//!     flick-effectsctl play confetti
+//!     flick-effectsctl play edge-glow --color '#e94560'
+//!     flick-effectsctl status --json
+//!     flick-effectsctl reload
+//!     flick-effectsctl set fire.palette plasma
+
+use glib::{ToVariant, Variant, VariantTy};
+use std::collections::HashMap;
+use std::process::ExitCode;
+
+// Must match src/dbus.rs
+const BUS_NAME: &str = "org.flick.Effects";
+const OBJECT_PATH: &str = "/org/flick/Effects";
+const INTERFACE: &str = "org.flick.Effects";
+
+/// How long to wait for the overlay before giving up, in milliseconds
+const CALL_TIMEOUT_MS: i32 = 5000;
+
+const NOT_RUNNING: &str = "flick-effects is not running";
+/// Exit status when the overlay is not running, so scripts can start it
+/// rather than treat every failure alike
+const EXIT_NOT_RUNNING: u8 = 3;
+
+const USAGE: &str = "\
+Usage: flick-effectsctl <command>
+
+Commands:
+  enable <feature>                 touch, living-pixels, stars, shooting-stars,
+  disable <feature>                fireflies, reduce-motion, flash-limit,
+                                   notifications, calls, audio or an
+                                   effect
+  trigger <effect> <x> <y> [--duration <seconds>]
+  play <effect> [--x <x>] [--y <y>] [--duration <seconds>] [--color <#rrggbb>]
+                                   fireworks, confetti, pulse, edge-glow or an
+                                   event effect file
+  status [--json]
+  reload                           re-read effects_config.json and effect files
+  set touch-effect <effect>
+  set <target>.palette <palette>   target is an effect, stars, shooting_stars
+  set <target>.gradient <json>     or fireflies
+
+Exits with status 3 if flick-effects is not running.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("flick-effectsctl: {}", e);
+            if e == NOT_RUNNING { ExitCode::from(EXIT_NOT_RUNNING) } else { ExitCode::FAILURE }
+        }
+    }
+}
+
+fn run(args: &[&str]) -> Result<(), String> {
+    match args {
+        ["enable", feature] => call("SetEnabled", Some((*feature, true).to_variant()), None).map(drop),
+        ["disable", feature] => call("SetEnabled", Some((*feature, false).to_variant()), None).map(drop),
+        ["trigger", name, x, y, rest @ ..] => {
+            let x: f64 = x.parse().map_err(|_| format!("bad x coordinate '{}'", x))?;
+            let y: f64 = y.parse().map_err(|_| format!("bad y coordinate '{}'", y))?;
+            let mut params = HashMap::<String, Variant>::new();
+            match rest {
+                [] => {}
+                ["--duration", seconds] => {
+                    let seconds: f64 = seconds.parse().map_err(|_| format!("bad duration '{}'", seconds))?;
+                    params.insert("duration".to_string(), seconds.to_variant());
+                }
+                _ => return Err(USAGE.to_string()),
+            }
+            call("TriggerEffect", Some((*name, x, y, params).to_variant()), None).map(drop)
+        }
+        ["play", name, rest @ ..] => {
+            let mut params = HashMap::<String, Variant>::new();
+            for option in rest.chunks(2) {
+                let [flag, value] = option else { return Err(USAGE.to_string()) };
+                let (key, value) = match *flag {
+                    "--x" | "--y" | "--duration" => {
+                        let number: f64 = value.parse().map_err(|_| format!("bad number for {}: '{}'", flag, value))?;
+                        (&flag[2..], number.to_variant())
+                    }
+                    "--color" => ("color", value.to_variant()),
+                    _ => return Err(USAGE.to_string()),
+                };
+                params.insert(key.to_string(), value);
+            }
+            call("Play", Some((*name, params).to_variant()), None).map(drop)
+        }
+        ["status"] => status(false),
+        ["status", "--json"] => status(true),
+        ["reload"] => call("ReloadConfig", None, None).map(drop),
+        ["set", "touch-effect", name] => call("SetEffect", Some((*name,).to_variant()), None).map(drop),
+        ["set", key, value] => {
+            let Some((target, "palette" | "gradient")) = key.rsplit_once('.') else {
+                return Err(format!("unknown setting '{}'\n\n{}", key, USAGE));
+            };
+            call("SetGradient", Some((target, *value).to_variant()), None).map(drop)
+        }
+        ["help" | "--help" | "-h"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// Call a method on the overlay and return its reply
+fn call(method: &str, params: Option<Variant>, reply_type: Option<&VariantTy>) -> Result<Variant, String> {
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)
+        .map_err(|e| format!("no session bus: {}", e))?;
+    connection
+        .call_sync(
+            Some(BUS_NAME),
+            OBJECT_PATH,
+            INTERFACE,
+            method,
+            params.as_ref(),
+            reply_type,
+            gio::DBusCallFlags::NONE,
+            CALL_TIMEOUT_MS,
+            gio::Cancellable::NONE,
+        )
+        .map_err(|e| describe_error(&e))
+}
+
+/// Turn "GDBus.Error:<name>: <message>" into something readable
+fn describe_error(error: &glib::Error) -> String {
+    let message = error.message();
+    let Some(remote) = message.strip_prefix("GDBus.Error:") else {
+        return message.to_string();
+    };
+    let (name, text) = remote.split_once(": ").unwrap_or((remote, ""));
+    match name {
+        "org.freedesktop.DBus.Error.ServiceUnknown" | "org.freedesktop.DBus.Error.NameHasNoOwner" => {
+            NOT_RUNNING.to_string()
+        }
+        _ if text.is_empty() => name.to_string(),
+        _ => text.to_string(),
+    }
+}
+
+fn status(json: bool) -> Result<(), String> {
+    let reply = call("GetStatus", None, Some(VariantTy::new("(a{sv})").expect("valid type")))?;
+    let (status,) = reply.get::<(HashMap<String, Variant>,)>().ok_or("unexpected GetStatus reply")?;
+    let mut keys: Vec<&String> = status.keys().collect();
+    keys.sort();
+
+    if json {
+        let object: serde_json::Map<String, serde_json::Value> =
+            keys.iter().map(|&key| (key.clone(), to_json(&status[key]))).collect();
+        println!("{}", serde_json::to_string_pretty(&object).expect("JSON values serialize"));
+    } else {
+        for key in keys {
+            let value = match to_json(&status[key]) {
+                serde_json::Value::String(s) => s,
+                serde_json::Value::Array(items) => {
+                    items.iter().map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string)).collect::<Vec<_>>().join(", ")
+                }
+                other => other.to_string(),
+            };
+            println!("{}: {}", key, value);
+        }
+    }
+    Ok(())
+}
+
+/// The value types GetStatus uses, as JSON
+fn to_json(value: &Variant) -> serde_json::Value {
+    if let Some(b) = value.get::<bool>() {
+        b.into()
+    } else if let Some(s) = value.get::<String>() {
+        s.into()
+    } else if let Some(list) = value.get::<Vec<String>>() {
+        list.into()
+    } else if let Some(n) = value.get::<u32>() {
+        n.into()
+    } else if let Some(d) = value.get::<f64>() {
+        serde_json::Number::from_f64(d).map_or(serde_json::Value::Null, Into::into)
+    } else {
+        value.print(false).to_string().into()
+    }
+}
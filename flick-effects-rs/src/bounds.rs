@@ -0,0 +1,67 @@
+/// Axis-aligned screen rectangle in logical pixels, used for damage tracking
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+impl Bounds {
+    /// Contains nothing; grows with `include`
+    pub fn empty() -> Self {
+        Self {
+            x0: f64::INFINITY,
+            y0: f64::INFINITY,
+            x1: f64::NEG_INFINITY,
+            y1: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Square of half-size `r` around a point
+    pub fn around(x: f64, y: f64, r: f64) -> Self {
+        Self {
+            x0: x - r,
+            y0: y - r,
+            x1: x + r,
+            y1: y + r,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x0 >= self.x1 || self.y0 >= self.y1
+    }
+
+    /// Grow to cover a circle of radius `r` at (x, y)
+    pub fn include(&mut self, x: f64, y: f64, r: f64) {
+        *self = self.union(&Self::around(x, y, r));
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            x0: self.x0.min(other.x0),
+            y0: self.y0.min(other.y0),
+            x1: self.x1.max(other.x1),
+            y1: self.y1.max(other.y1),
+        }
+    }
+
+    /// Snap outwards to whole pixels (plus a pixel of antialiasing slack)
+    /// and clip to the screen
+    pub fn to_pixels(self, width: i32, height: i32) -> Bounds {
+        Bounds {
+            x0: (self.x0.floor() - 1.0).max(0.0),
+            y0: (self.y0.floor() - 1.0).max(0.0),
+            x1: (self.x1.ceil() + 1.0).min(width as f64),
+            y1: (self.y1.ceil() + 1.0).min(height as f64),
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.x1 - self.x0
+    }
+
+    pub fn height(&self) -> f64 {
+        self.y1 - self.y0
+    }
+}
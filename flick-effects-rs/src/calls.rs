@@ -0,0 +1,207 @@
+use gio::prelude::*;
+use glib::Variant;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const MM_SERVICE: &str = "org.freedesktop.ModemManager1";
+const MM_PATH: &str = "/org/freedesktop/ModemManager1";
+const CALL_INTERFACE: &str = "org.freedesktop.ModemManager1.Call";
+const VOICE_INTERFACE: &str = "org.freedesktop.ModemManager1.Modem.Voice";
+
+/// Color of the ring shown for incoming calls
+pub const RING_COLOR: [f64; 3] = [0.2, 0.85, 0.4];
+
+/// MMCallState values for an incoming call that hasn't been answered yet
+const STATE_RINGING_IN: i32 = 3;
+const STATE_WAITING: i32 = 6;
+
+/// Follows ModemManager's voice calls on the system bus and reports
+/// whenever an incoming call starts or stops ringing. Pointing
+/// DBUS_SYSTEM_BUS_ADDRESS at another bus runs it against a mock
+/// (`cargo run --example mock-modemmanager`). Watching stops when this is
+/// dropped.
+pub struct CallWatcher {
+    connection: gio::DBusConnection,
+    subscriptions: Vec<gio::SignalSubscriptionId>,
+    calls: Rc<Calls>,
+}
+
+/// Call states by object path, and whether any of them was ringing when
+/// last reported
+struct Calls {
+    states: RefCell<HashMap<String, i32>>,
+    ringing: Cell<bool>,
+    on_ringing: Box<dyn Fn(bool)>,
+}
+
+impl Calls {
+    fn set(&self, path: &str, state: Option<i32>) {
+        {
+            let mut states = self.states.borrow_mut();
+            match state {
+                Some(state) => states.insert(path.to_string(), state),
+                None => states.remove(path),
+            };
+        }
+        self.report();
+    }
+
+    /// Forget every call, e.g. when ModemManager goes away
+    fn clear(&self) {
+        self.states.borrow_mut().clear();
+        self.report();
+    }
+
+    fn report(&self) {
+        let ringing = self.states.borrow().values().any(|&s| s == STATE_RINGING_IN || s == STATE_WAITING);
+        if ringing != self.ringing.get() {
+            self.ringing.set(ringing);
+            println!("Incoming call {}", if ringing { "ringing" } else { "no longer ringing" });
+            (self.on_ringing)(ringing);
+        }
+    }
+}
+
+impl CallWatcher {
+    pub fn new<F>(on_ringing: F) -> Result<Self, glib::Error>
+    where
+        F: Fn(bool) + 'static,
+    {
+        let connection = gio::bus_get_sync(gio::BusType::System, gio::Cancellable::NONE)?;
+        let calls = Rc::new(Calls {
+            states: RefCell::new(HashMap::new()),
+            ringing: Cell::new(false),
+            on_ringing: Box::new(on_ringing),
+        });
+
+        let mut subscriptions = Vec::new();
+        let calls_state = calls.clone();
+        subscriptions.push(connection.signal_subscribe(
+            Some(MM_SERVICE),
+            Some(CALL_INTERFACE),
+            Some("StateChanged"),
+            None,
+            None,
+            gio::DBusSignalFlags::NONE,
+            move |_, _, path, _, _, params| {
+                if let Some((_old, new, _reason)) = params.get::<(i32, i32, u32)>() {
+                    calls_state.set(path, Some(new));
+                }
+            },
+        ));
+
+        // New calls can start out ringing, without a StateChanged
+        let calls_added = calls.clone();
+        subscriptions.push(connection.signal_subscribe(
+            Some(MM_SERVICE),
+            Some(VOICE_INTERFACE),
+            Some("CallAdded"),
+            None,
+            None,
+            gio::DBusSignalFlags::NONE,
+            move |connection, _, _, _, _, params| {
+                let Some(path) = params.child_value(0).get::<glib::variant::ObjectPath>() else { return };
+                let calls = calls_added.clone();
+                let path = path.as_str().to_string();
+                connection.call(
+                    Some(MM_SERVICE),
+                    &path.clone(),
+                    "org.freedesktop.DBus.Properties",
+                    "Get",
+                    Some(&(CALL_INTERFACE, "State").to_variant()),
+                    None,
+                    gio::DBusCallFlags::NO_AUTO_START,
+                    1000,
+                    gio::Cancellable::NONE,
+                    move |result| {
+                        let state = result.ok().and_then(|reply| reply.child_value(0).as_variant()?.get::<i32>());
+                        if let Some(state) = state {
+                            calls.set(&path, Some(state));
+                        }
+                    },
+                );
+            },
+        ));
+
+        let calls_deleted = calls.clone();
+        subscriptions.push(connection.signal_subscribe(
+            Some(MM_SERVICE),
+            Some(VOICE_INTERFACE),
+            Some("CallDeleted"),
+            None,
+            None,
+            gio::DBusSignalFlags::NONE,
+            move |_, _, _, _, _, params| {
+                if let Some(path) = params.child_value(0).get::<glib::variant::ObjectPath>() {
+                    calls_deleted.set(path.as_str(), None);
+                }
+            },
+        ));
+
+        // ModemManager restarting: its calls went with it, and any new ones
+        // are listed again once it is back
+        let calls_owner = calls.clone();
+        subscriptions.push(connection.signal_subscribe(
+            Some("org.freedesktop.DBus"),
+            Some("org.freedesktop.DBus"),
+            Some("NameOwnerChanged"),
+            Some("/org/freedesktop/DBus"),
+            Some(MM_SERVICE),
+            gio::DBusSignalFlags::NONE,
+            move |connection, _, _, _, _, params| {
+                let Some((_, _, new_owner)) = params.get::<(String, String, String)>() else { return };
+                calls_owner.clear();
+                if !new_owner.is_empty() {
+                    list_calls(connection, calls_owner.clone());
+                }
+            },
+        ));
+
+        // Calls that were already there when we started
+        list_calls(&connection, calls.clone());
+
+        Ok(Self { connection, subscriptions, calls })
+    }
+
+    /// Whether an incoming call is ringing right now
+    pub fn is_ringing(&self) -> bool {
+        self.calls.ringing.get()
+    }
+}
+
+/// Record the calls ModemManager has now
+fn list_calls(connection: &gio::DBusConnection, calls: Rc<Calls>) {
+    connection.call(
+        Some(MM_SERVICE),
+        MM_PATH,
+        "org.freedesktop.DBus.ObjectManager",
+        "GetManagedObjects",
+        None,
+        None,
+        gio::DBusCallFlags::NO_AUTO_START,
+        1000,
+        gio::Cancellable::NONE,
+        move |result| {
+            // No ModemManager is fine; its calls will be seen once it starts
+            let Ok(reply) = result else { return };
+            let Some((objects,)) = reply.get::<(HashMap<glib::variant::ObjectPath, HashMap<String, HashMap<String, Variant>>>,)>() else {
+                return;
+            };
+            for (path, interfaces) in objects {
+                let state = interfaces.get(CALL_INTERFACE).and_then(|p| p.get("State")?.get::<i32>());
+                if let Some(state) = state {
+                    calls.set(path.as_str(), Some(state));
+                }
+            }
+        },
+    );
+}
+
+impl Drop for CallWatcher {
+    fn drop(&mut self) {
+        for id in self.subscriptions.drain(..) {
+            self.connection.signal_unsubscribe(id);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::{cairo, glib, graphene};
+
+use crate::bounds::Bounds;
+
+type DrawFunc = Box<dyn Fn(&mut Layers, i32, i32)>;
+
+mod imp {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    pub struct EffectsCanvas {
+        pub draw_func: RefCell<Option<DrawFunc>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for EffectsCanvas {
+        const NAME: &'static str = "FlickEffectsCanvas";
+        type Type = super::EffectsCanvas;
+        type ParentType = gtk4::Widget;
+    }
+
+    impl ObjectImpl for EffectsCanvas {}
+
+    impl WidgetImpl for EffectsCanvas {
+        fn snapshot(&self, snapshot: &gtk4::Snapshot) {
+            let widget = self.obj();
+            if let Some(draw_func) = self.draw_func.borrow().as_ref() {
+                let mut layers = Layers {
+                    snapshot,
+                    width: widget.width(),
+                    height: widget.height(),
+                };
+                draw_func(&mut layers, widget.width(), widget.height());
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Cairo canvas that gives every effect its own render node sized to
+    /// the effect's bounds. GTK diffs the node tree between frames, so only
+    /// the union of old and new bounds is repainted and damaged, rather than
+    /// the whole fullscreen overlay.
+    pub struct EffectsCanvas(ObjectSubclass<imp::EffectsCanvas>)
+        @extends gtk4::Widget;
+}
+
+impl EffectsCanvas {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// `func` receives the layer list and widget size once per frame
+    pub fn set_draw_func<F>(&self, func: F)
+    where
+        F: Fn(&mut Layers, i32, i32) + 'static,
+    {
+        *self.imp().draw_func.borrow_mut() = Some(Box::new(func));
+        self.queue_draw();
+    }
+}
+
+impl Default for EffectsCanvas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Per-frame painter handed to the draw function
+pub struct Layers<'a> {
+    snapshot: &'a gtk4::Snapshot,
+    width: i32,
+    height: i32,
+}
+
+impl Layers<'_> {
+    /// Paint one effect into a fresh transparent node covering `bounds`.
+    /// Anything drawn outside `bounds` is clipped.
+    pub fn paint<F>(&mut self, bounds: Bounds, draw: F)
+    where
+        F: FnOnce(&cairo::Context),
+    {
+        let bounds = bounds.to_pixels(self.width, self.height);
+        if bounds.is_empty() {
+            return;
+        }
+
+        let rect = graphene::Rect::new(
+            bounds.x0 as f32,
+            bounds.y0 as f32,
+            bounds.width() as f32,
+            bounds.height() as f32,
+        );
+        let cr = self.snapshot.append_cairo(&rect);
+        draw(&cr);
+    }
+}
@@ -72,6 +72,12 @@ impl ScreenAnalyzer {
     }
 }
 
+impl Default for ScreenAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 fn do_capture_analysis() -> Option<AnalysisData> {
     // Use grim for quick capture - just need rough brightness data
     let output = std::process::Command::new("grim")
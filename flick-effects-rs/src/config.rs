@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+
+use crate::gradient::Gradient;
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -8,6 +11,68 @@ pub struct Config {
     #[serde(default = "default_true")]
     pub fire_touch_enabled: bool,
 
+    // Effect started by touches, by name (see ~/.local/share/flick/effects)
+    #[serde(default = "default_touch_effect")]
+    pub touch_effect: String,
+    // Effect files with an ambient trigger to run continuously
+    #[serde(default)]
+    pub ambient_effects: Vec<String>,
+
+    // Rhai scripts to run, by name (<name>.rhai in the effects directory)
+    #[serde(default)]
+    pub script_effects: Vec<String>,
+    // Longest a single script call may run before it is aborted
+    #[serde(default = "default_script_time_limit_ms")]
+    pub script_time_limit_ms: f64,
+
+    // Color overrides by effect name (or "stars", "shooting_stars",
+    // "fireflies"): a palette name, a list of stops, or
+    // { "palette"/"stops": ..., "interpolation": "linear" | "oklab" }
+    #[serde(default)]
+    pub gradients: HashMap<String, Gradient>,
+
+    // Tint fire, fireflies and stars with the theme accent color from
+    // display_config.json (explicit gradients still win)
+    #[serde(default = "default_true")]
+    pub follow_accent: bool,
+
+    // Adapt colors to the GNOME light color scheme, high contrast and
+    // night light settings
+    #[serde(default = "default_true")]
+    pub follow_appearance: bool,
+
+    // Replace moving effects with still ones; also on whenever GNOME's
+    // enable-animations is off
+    #[serde(default)]
+    pub reduce_motion: bool,
+
+    // Dim any part of the screen that would flash more than three times a
+    // second (photosensitivity safeguard, WCAG 2.3.1)
+    #[serde(default = "default_true")]
+    pub flash_limit: bool,
+
+    // Programs allowed to request effects over D-Bus (TriggerEffect, Play),
+    // by executable name; "*" allows any. None by default. Programs can
+    // choose their own name, so this only guards against accidents
+    #[serde(default = "default_trigger_clients")]
+    pub trigger_clients: Vec<String>,
+    // Requests each of them may make per minute after an initial burst
+    #[serde(default = "default_trigger_rate")]
+    pub trigger_rate_per_minute: f64,
+
+    // Glow along the screen edges when a notification arrives, colored by
+    // urgency; skipped while do-not-disturb is on (show-banners off)
+    #[serde(default)]
+    pub notification_glow: bool,
+    // Per-app overrides, keyed by desktop entry or app name: false to
+    // ignore the app, or a "#rrggbb" color
+    #[serde(default)]
+    pub notification_apps: HashMap<String, AppGlow>,
+
+    // Ring from the screen edges while an incoming call is ringing
+    #[serde(default = "default_true")]
+    pub call_ring: bool,
+
     // Living pixels - ambient effects
     #[serde(default)]
     pub living_pixels_enabled: bool,
@@ -17,18 +82,121 @@ pub struct Config {
     pub lp_shooting_stars: bool,
     #[serde(default = "default_true")]
     pub lp_fireflies: bool,
+    // Fireflies pulse on beats and stars twinkle with treble while a media
+    // player is playing; listens to the default sink's monitor
+    #[serde(default)]
+    pub audio_reactive: bool,
+    // Spectrum shown while a media player is playing, tinted by the cover art
+    #[serde(default)]
+    pub visualizer: Visualizer,
+
+    // Drawing backend; GL falls back to cairo if unavailable
+    #[serde(default)]
+    pub renderer: Renderer,
+
+    // Read the touchscreen from the main loop instead of a helper thread
+    #[serde(default)]
+    pub touch_in_main_loop: bool,
+
+    // RNG seed for reproducible effects (random per run when unset)
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    // Most particles alive at once, shared between all effects
+    #[serde(default = "default_particle_budget")]
+    pub particle_budget: usize,
+
+    // Adaptive quality: detail is scaled between this floor and ceiling
+    // (1.0 = full) to keep each frame's tick + draw time under the budget
+    #[serde(default = "default_quality_min")]
+    pub quality_min: f64,
+    #[serde(default = "default_quality_max")]
+    pub quality_max: f64,
+    #[serde(default = "default_frame_budget_ms")]
+    pub frame_budget_ms: f64,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Renderer {
+    #[default]
+    Cairo,
+    Gl,
+}
+
+impl std::str::FromStr for Renderer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cairo" => Ok(Renderer::Cairo),
+            "gl" => Ok(Renderer::Gl),
+            _ => Err(format!("unknown renderer '{}' (expected cairo or gl)", s)),
+        }
+    }
+}
+
+/// Where the now-playing spectrum is drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visualizer {
+    #[default]
+    Off,
+    /// Bars along the bottom edge
+    Bottom,
+    /// Bars around a ring in the middle of the screen
+    Ring,
+}
+
+/// Notification glow setting for one app
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AppGlow {
+    Enabled(bool),
+    Color(String),
 }
 
 fn default_true() -> bool { true }
+fn default_touch_effect() -> String { "fire".to_string() }
+fn default_script_time_limit_ms() -> f64 { 4.0 }
+fn default_trigger_clients() -> Vec<String> { Vec::new() }
+fn default_trigger_rate() -> f64 { 20.0 }
+fn default_particle_budget() -> usize { 240 }
+fn default_quality_min() -> f64 { 0.25 }
+fn default_quality_max() -> f64 { 1.0 }
+fn default_frame_budget_ms() -> f64 { 8.0 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             fire_touch_enabled: true,
+            touch_effect: default_touch_effect(),
+            ambient_effects: Vec::new(),
+            script_effects: Vec::new(),
+            script_time_limit_ms: default_script_time_limit_ms(),
+            gradients: HashMap::new(),
+            follow_accent: true,
+            follow_appearance: true,
+            reduce_motion: false,
+            flash_limit: true,
+            trigger_clients: default_trigger_clients(),
+            trigger_rate_per_minute: default_trigger_rate(),
+            notification_glow: false,
+            notification_apps: HashMap::new(),
+            call_ring: true,
             living_pixels_enabled: false,
             lp_stars: true,
             lp_shooting_stars: true,
             lp_fireflies: true,
+            audio_reactive: false,
+            visualizer: Visualizer::default(),
+            renderer: Renderer::default(),
+            touch_in_main_loop: false,
+            seed: None,
+            particle_budget: default_particle_budget(),
+            quality_min: default_quality_min(),
+            quality_max: default_quality_max(),
+            frame_budget_ms: default_frame_budget_ms(),
         }
     }
 }
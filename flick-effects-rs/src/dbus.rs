@@ -0,0 +1,436 @@
+use glib::thread_guard::ThreadGuard;
+use glib::{ToVariant, Variant, VariantTy};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::theme::parse_hex_color;
+
+/// Well-known name, object path and interface of the control API. The
+/// GApplication already owns the name; the interface sits next to its
+/// own ones on the application's object.
+pub const BUS_NAME: &str = "org.flick.Effects";
+pub const OBJECT_PATH: &str = "/org/flick/Effects";
+pub const INTERFACE: &str = "org.flick.Effects";
+
+const INTROSPECTION: &str = r#"
+<node>
+  <interface name="org.flick.Effects">
+    <!-- feature: touch, living-pixels, stars, shooting-stars, fireflies,
+         reduce-motion, flash-limit, notifications, calls, audio, or the name
+         of an effect -->
+    <method name="SetEnabled">
+      <arg name="feature" type="s" direction="in"/>
+      <arg name="enabled" type="b" direction="in"/>
+    </method>
+    <!-- Effect started by touches -->
+    <method name="SetEffect">
+      <arg name="name" type="s" direction="in"/>
+    </method>
+    <!-- Recolor an effect or stars/shooting_stars/fireflies; gradient is a
+         palette name or a gradient in the config's JSON form -->
+    <method name="SetGradient">
+      <arg name="target" type="s" direction="in"/>
+      <arg name="gradient" type="s" direction="in"/>
+    </method>
+    <!-- Re-read effects_config.json and the effect files -->
+    <method name="ReloadConfig"/>
+    <!-- Touch or event effect at a point; params: duration (d, seconds) -->
+    <method name="TriggerEffect">
+      <arg name="name" type="s" direction="in"/>
+      <arg name="x" type="d" direction="in"/>
+      <arg name="y" type="d" direction="in"/>
+      <arg name="params" type="a{sv}" direction="in"/>
+    </method>
+    <!-- Named one-shot effect: fireworks, confetti, pulse, edge-glow or any
+         event effect file; params: x, y (d), duration (d, seconds) and
+         color (s, #rrggbb, for pulse and edge-glow) -->
+    <method name="Play">
+      <arg name="name" type="s" direction="in"/>
+      <arg name="params" type="a{sv}" direction="in"/>
+    </method>
+    <method name="GetStatus">
+      <arg name="status" type="a{sv}" direction="out"/>
+    </method>
+    <property name="TouchEnabled" type="b" access="read"/>
+    <property name="TouchEffect" type="s" access="read"/>
+    <property name="AmbientEffects" type="as" access="read"/>
+    <property name="LivingPixels" type="b" access="read"/>
+    <property name="ReduceMotion" type="b" access="read"/>
+    <property name="AvailableEffects" type="as" access="read"/>
+  </interface>
+</node>
+"#;
+
+/// Snapshot of the overlay's settings and activity
+pub struct Status {
+    pub touch_enabled: bool,
+    pub touch_effect: String,
+    pub ambient_effects: Vec<String>,
+    pub living_pixels: bool,
+    pub reduce_motion: bool,
+    pub available_effects: Vec<String>,
+    pub scripts: Vec<String>,
+    // Running effect instances and live particles
+    pub active_effects: u32,
+    pub particles: u32,
+    pub quality: f64,
+}
+
+impl Status {
+    /// Values exposed as D-Bus properties
+    fn properties(&self) -> HashMap<String, Variant> {
+        HashMap::from([
+            ("TouchEnabled".to_string(), self.touch_enabled.to_variant()),
+            ("TouchEffect".to_string(), self.touch_effect.to_variant()),
+            ("AmbientEffects".to_string(), self.ambient_effects.to_variant()),
+            ("LivingPixels".to_string(), self.living_pixels.to_variant()),
+            ("ReduceMotion".to_string(), self.reduce_motion.to_variant()),
+            ("AvailableEffects".to_string(), self.available_effects.to_variant()),
+        ])
+    }
+
+    /// GetStatus reply: the properties plus activity counters
+    fn to_dict(&self) -> HashMap<String, Variant> {
+        HashMap::from([
+            ("touch_enabled".to_string(), self.touch_enabled.to_variant()),
+            ("touch_effect".to_string(), self.touch_effect.to_variant()),
+            ("ambient_effects".to_string(), self.ambient_effects.to_variant()),
+            ("living_pixels".to_string(), self.living_pixels.to_variant()),
+            ("reduce_motion".to_string(), self.reduce_motion.to_variant()),
+            ("available_effects".to_string(), self.available_effects.to_variant()),
+            ("scripts".to_string(), self.scripts.to_variant()),
+            ("active_effects".to_string(), self.active_effects.to_variant()),
+            ("particles".to_string(), self.particles.to_variant()),
+            ("quality".to_string(), self.quality.to_variant()),
+        ])
+    }
+}
+
+/// Options for TriggerEffect
+pub struct TriggerParams {
+    // Seconds before the effect is released and fades out
+    pub duration: f64,
+}
+
+impl TriggerParams {
+    fn parse(params: &HashMap<String, Variant>) -> Result<Self, String> {
+        let mut parsed = TriggerParams { duration: 0.5 };
+        for (key, value) in params {
+            match key.as_str() {
+                "duration" => {
+                    parsed.duration = value
+                        .get::<f64>()
+                        .filter(|d| d.is_finite() && *d > 0.0 && *d <= 10.0)
+                        .ok_or("duration must be a double between 0 and 10 seconds")?;
+                }
+                _ => return Err(format!("unknown parameter '{}'", key)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Options for Play; what is left out comes from the effect
+#[derive(Default)]
+pub struct PlayParams {
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub duration: Option<f64>,
+    pub color: Option<[f64; 3]>,
+}
+
+impl PlayParams {
+    fn parse(params: &HashMap<String, Variant>) -> Result<Self, String> {
+        let mut parsed = PlayParams::default();
+        for (key, value) in params {
+            match key.as_str() {
+                "x" | "y" => {
+                    let v = value.get::<f64>().filter(|v| v.is_finite()).ok_or("x and y must be finite doubles")?;
+                    if key == "x" { parsed.x = Some(v) } else { parsed.y = Some(v) }
+                }
+                "duration" => {
+                    parsed.duration = Some(
+                        value
+                            .get::<f64>()
+                            .filter(|d| d.is_finite() && *d > 0.0 && *d <= 10.0)
+                            .ok_or("duration must be a double between 0 and 10 seconds")?,
+                    );
+                }
+                "color" => {
+                    parsed.color = Some(
+                        value
+                            .get::<String>()
+                            .and_then(|s| parse_hex_color(&s))
+                            .ok_or("color must be a string like '#ff8800'")?,
+                    );
+                }
+                _ => return Err(format!("unknown parameter '{}'", key)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Who may request effects (TriggerEffect, Play), and how often
+pub struct ClientPolicy {
+    // Executable names; "*" allows any. Advisory: see Gate
+    pub allowed: Vec<String>,
+    pub per_minute: f64,
+}
+
+/// What the D-Bus interface can ask of the overlay. Errors are returned
+/// to the caller as InvalidArgs. Methods get the shared handle so an
+/// implementation can keep a reference to itself in callbacks.
+pub trait Controller {
+    fn set_enabled(self: &Rc<Self>, feature: &str, enabled: bool) -> Result<(), String>;
+    fn set_effect(self: &Rc<Self>, name: &str) -> Result<(), String>;
+    fn set_gradient(self: &Rc<Self>, target: &str, gradient: &str) -> Result<(), String>;
+    /// Also emits PropertiesChanged (see `properties_changed`)
+    fn reload_config(self: &Rc<Self>);
+    fn trigger_effect(self: &Rc<Self>, name: &str, x: f64, y: f64, params: &TriggerParams) -> Result<(), String>;
+    fn play(self: &Rc<Self>, name: &str, params: &PlayParams) -> Result<(), String>;
+    fn client_policy(self: &Rc<Self>) -> ClientPolicy;
+    fn status(self: &Rc<Self>) -> Status;
+}
+
+const INVALID_ARGS: &str = "org.freedesktop.DBus.Error.InvalidArgs";
+const ACCESS_DENIED: &str = "org.freedesktop.DBus.Error.AccessDenied";
+const LIMITS_EXCEEDED: &str = "org.freedesktop.DBus.Error.LimitsExceeded";
+
+/// Effect requests a client may make back to back before the rate applies
+const REQUEST_BURST: f64 = 5.0;
+/// Callers remembered before the sender cache starts over
+const MAX_KNOWN_SENDERS: usize = 256;
+
+/// Checks effect requests against the allow-list and a token bucket per
+/// client. Clients are told apart by executable name, so a script that
+/// starts a new process per request still shares one bucket. Any process
+/// can run under a name of its choosing, so the allow-list only keeps
+/// well-behaved programs from requesting effects they weren't meant to;
+/// it is no defence against a hostile one on the session bus.
+struct Gate {
+    // Unique bus name to executable name
+    senders: HashMap<String, String>,
+    // Executable name to (tokens left, when last refilled)
+    buckets: HashMap<String, (f64, Instant)>,
+    // Clients already logged as refused, to log each only once
+    refused: HashSet<String>,
+}
+
+impl Gate {
+    fn new() -> Self {
+        Self {
+            senders: HashMap::new(),
+            buckets: HashMap::new(),
+            refused: HashSet::new(),
+        }
+    }
+
+    /// Executable name already looked up for a unique bus name
+    fn known(&self, sender: &str) -> Option<String> {
+        self.senders.get(sender).cloned()
+    }
+
+    fn remember(&mut self, sender: &str, client: &str) {
+        if self.senders.len() >= MAX_KNOWN_SENDERS {
+            self.senders.clear();
+        }
+        self.senders.insert(sender.to_string(), client.to_string());
+    }
+
+    /// Ok, or the D-Bus error name and message to reply with
+    fn admit(&mut self, client: &str, policy: &ClientPolicy) -> Result<(), (&'static str, String)> {
+        if !policy.allowed.iter().any(|a| a == "*" || a == client) {
+            if self.refused.insert(client.to_string()) {
+                eprintln!("Refusing effect requests from '{}' (not in trigger_clients)", client);
+            }
+            return Err((ACCESS_DENIED, format!("'{}' is not allowed to request effects", client)));
+        }
+
+        let now = Instant::now();
+        let (tokens, last) = self.buckets.entry(client.to_string()).or_insert((REQUEST_BURST, now));
+        let refill = now.duration_since(*last).as_secs_f64() * policy.per_minute / 60.0;
+        *tokens = (*tokens + refill).min(REQUEST_BURST);
+        *last = now;
+        if *tokens < 1.0 {
+            return Err((LIMITS_EXCEEDED, format!("too many effect requests from '{}'", client)));
+        }
+        *tokens -= 1.0;
+        Ok(())
+    }
+}
+
+/// Look up the executable name of the process behind a unique bus name
+/// and hand it to `done`, without blocking the main loop
+fn identify<F>(connection: &gio::DBusConnection, sender: &str, done: F)
+where
+    F: FnOnce(Result<String, String>) + 'static,
+{
+    connection.call(
+        Some("org.freedesktop.DBus"),
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "GetConnectionCredentials",
+        Some(&(sender,).to_variant()),
+        Some(VariantTy::new("(a{sv})").expect("valid type")),
+        gio::DBusCallFlags::NONE,
+        1000,
+        gio::Cancellable::NONE,
+        move |result| {
+            let client = result
+                .map_err(|e| format!("cannot identify caller: {}", e))
+                .and_then(|reply| executable_name(&reply));
+            done(client);
+        },
+    );
+}
+
+/// Executable name from a GetConnectionCredentials reply
+fn executable_name(credentials: &Variant) -> Result<String, String> {
+    let (credentials,) = credentials.get::<(HashMap<String, Variant>,)>().ok_or("cannot identify caller")?;
+    let pid = credentials.get("ProcessID").and_then(|p| p.get::<u32>()).ok_or("cannot identify caller")?;
+    let exe = std::fs::read_link(format!("/proc/{}/exe", pid)).map_err(|e| format!("cannot identify caller: {}", e))?;
+    let name = exe.file_name().ok_or("cannot identify caller")?.to_string_lossy();
+    // Binaries replaced by an upgrade while running
+    Ok(name.trim_end_matches(" (deleted)").to_string())
+}
+
+/// Serve the control interface on `connection` for as long as it is open
+pub fn export<C: Controller + 'static>(connection: &gio::DBusConnection, controller: Rc<C>) -> Result<(), glib::Error> {
+    let node = gio::DBusNodeInfo::for_xml(INTROSPECTION)?;
+    let interface = node.lookup_interface(INTERFACE).expect("interface in introspection data");
+
+    // gio wants Send + Sync callbacks, but calls them on this thread's main
+    // context; the guard panics if that ever changes, and the mutex is only
+    // there to make it Sync
+    let methods = Mutex::new(ThreadGuard::new(controller.clone()));
+    let properties = Mutex::new(ThreadGuard::new(controller));
+    let gate = Arc::new(Mutex::new(Gate::new()));
+
+    connection.register_object(
+        OBJECT_PATH,
+        &interface,
+        move |connection, sender, _path, _interface, method, params, invocation| {
+            let controller = methods.lock().unwrap().get_ref().clone();
+            if !matches!(method, "TriggerEffect" | "Play") {
+                respond(&connection, &controller, method, &params, invocation);
+                return;
+            }
+
+            // Effect requests pass the gate first; a new caller is looked up
+            // on the bus, and the reply is sent once that comes back
+            let known = gate.lock().unwrap().known(sender);
+            let admit_gate = gate.clone();
+            let lookup = connection.clone();
+            let method = method.to_string();
+            let admit = move |client: Result<String, String>| {
+                let admitted = client
+                    .map_err(|e| (ACCESS_DENIED, e))
+                    .and_then(|client| admit_gate.lock().unwrap().admit(&client, &controller.client_policy()));
+                match admitted {
+                    Ok(()) => respond(&connection, &controller, &method, &params, invocation),
+                    Err((error, message)) => invocation.return_dbus_error(error, &message),
+                }
+            };
+            match known {
+                Some(client) => admit(Ok(client)),
+                None => {
+                    let gate = gate.clone();
+                    let name = sender.to_string();
+                    identify(&lookup, sender, move |client| {
+                        if let Ok(client) = &client {
+                            gate.lock().unwrap().remember(&name, client);
+                        }
+                        admit(client);
+                    });
+                }
+            }
+        },
+        move |_connection, _sender, _path, _interface, property| {
+            let status = properties.lock().unwrap().get_ref().status();
+            status.properties().remove(property).unwrap_or_else(|| false.to_variant())
+        },
+        |_connection, _sender, _path, _interface, _property, _value| false,
+    )?;
+
+    println!("Control interface on D-Bus at {} {}", BUS_NAME, OBJECT_PATH);
+    Ok(())
+}
+
+/// Run one method call and send its reply
+fn respond<C: Controller>(
+    connection: &gio::DBusConnection,
+    controller: &Rc<C>,
+    method: &str,
+    params: &Variant,
+    invocation: gio::DBusMethodInvocation,
+) {
+    match call(controller, method, params) {
+        Ok(reply) => {
+            invocation.return_value(reply.as_ref());
+            // Reloads also come from SIGHUP, so the controller reports those
+            if matches!(method, "SetEnabled" | "SetEffect" | "SetGradient") {
+                properties_changed(connection, &controller.status());
+            }
+        }
+        Err(e) => invocation.return_dbus_error(INVALID_ARGS, &e),
+    }
+}
+
+/// Dispatch one method call; returns the reply body, if any
+fn call<C: Controller>(controller: &Rc<C>, method: &str, params: &Variant) -> Result<Option<Variant>, String> {
+    let bad_args = || format!("bad arguments for {}: {}", method, params.type_());
+    match method {
+        "SetEnabled" => {
+            let (feature, enabled) = params.get::<(String, bool)>().ok_or_else(bad_args)?;
+            controller.set_enabled(&feature, enabled)?;
+            Ok(None)
+        }
+        "SetEffect" => {
+            let (name,) = params.get::<(String,)>().ok_or_else(bad_args)?;
+            controller.set_effect(&name)?;
+            Ok(None)
+        }
+        "SetGradient" => {
+            let (target, gradient) = params.get::<(String, String)>().ok_or_else(bad_args)?;
+            controller.set_gradient(&target, &gradient)?;
+            Ok(None)
+        }
+        "ReloadConfig" => {
+            controller.reload_config();
+            Ok(None)
+        }
+        "TriggerEffect" => {
+            let (name, x, y, params) = params
+                .get::<(String, f64, f64, HashMap<String, Variant>)>()
+                .ok_or_else(bad_args)?;
+            controller.trigger_effect(&name, x, y, &TriggerParams::parse(&params)?)?;
+            Ok(None)
+        }
+        "Play" => {
+            let (name, params) = params.get::<(String, HashMap<String, Variant>)>().ok_or_else(bad_args)?;
+            controller.play(&name, &PlayParams::parse(&params)?)?;
+            Ok(None)
+        }
+        "GetStatus" => Ok(Some((controller.status().to_dict(),).to_variant())),
+        _ => Err(format!("unknown method {}", method)),
+    }
+}
+
+/// Tell clients the properties may have changed, for changes that did not
+/// come in through a method call
+pub fn properties_changed(connection: &gio::DBusConnection, status: &Status) {
+    let body = (INTERFACE, status.properties(), Vec::<String>::new()).to_variant();
+    let result = connection.emit_signal(
+        None,
+        OBJECT_PATH,
+        "org.freedesktop.DBus.Properties",
+        "PropertiesChanged",
+        Some(&body),
+    );
+    if let Err(e) = result {
+        eprintln!("Failed to emit PropertiesChanged: {}", e);
+    }
+}
@@ -0,0 +1,243 @@
+use gio::prelude::*;
+use glib::Variant;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::emitter::Blend;
+use crate::gradient::{oklab_to_srgb, srgb_to_oklab};
+
+const INTERFACE_SCHEMA: &str = "org.gnome.desktop.interface";
+const A11Y_INTERFACE_SCHEMA: &str = "org.gnome.desktop.a11y.interface";
+const COLOR_SCHEMA: &str = "org.gnome.settings-daemon.plugins.color";
+/// gsd's color plugin, which knows whether night light is on right now
+const COLOR_BUS_NAME: &str = "org.gnome.SettingsDaemon.Color";
+const COLOR_PATH: &str = "/org/gnome/SettingsDaemon/Color";
+
+/// Color temperature night light treats as neutral, in Kelvin
+const NEUTRAL_TEMPERATURE: f64 = 6500.0;
+/// Warmest temperature gsd allows
+const WARMEST_TEMPERATURE: f64 = 1700.0;
+
+/// Desktop appearance preferences that change how effects look and move
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Appearance {
+    // color-scheme is prefer-light
+    pub light: bool,
+    pub high_contrast: bool,
+    // How far night light has shifted the display, 0 (off) to 1 (warmest)
+    pub warmth: f64,
+    // enable-animations is off
+    pub reduce_motion: bool,
+}
+
+impl Appearance {
+    /// Adapt an effect color: darker on light backgrounds, pushed away
+    /// from the background in high contrast, and
+    /// with blue pulled out while night light is on
+    pub fn color(&self, (r, g, b): (f64, f64, f64)) -> (f64, f64, f64) {
+        if !self.light && !self.high_contrast && self.warmth == 0.0 {
+            return (r, g, b);
+        }
+        // Only lightness moves, so hues survive without leaving the gamut
+        let [mut l, a, b] = srgb_to_oklab([r, g, b]);
+        if self.light {
+            l *= 0.65;
+        }
+        if self.high_contrast {
+            l = if self.light { l.min(0.4) } else { l.max(0.8) };
+        }
+        let [r, g, b] = oklab_to_srgb([l, a, b]);
+        let w = self.warmth;
+        (r, g * (1.0 - 0.2 * w), b * (1.0 - 0.6 * w))
+    }
+
+    /// High contrast makes faint particles more opaque
+    pub fn alpha(&self, alpha: f64) -> f64 {
+        if self.high_contrast { alpha.clamp(0.0, 1.0).sqrt() } else { alpha }
+    }
+
+    /// Additive blending only brightens, which disappears on a light background
+    pub fn blend(&self, blend: Blend) -> Blend {
+        if self.light { Blend::Over } else { blend }
+    }
+
+    /// Color of outlines drawn around touches in high contrast mode
+    pub fn outline(&self) -> Option<(f64, f64, f64)> {
+        match (self.high_contrast, self.light) {
+            (false, _) => None,
+            (true, true) => Some((0.0, 0.0, 0.0)),
+            (true, false) => Some((1.0, 1.0, 1.0)),
+        }
+    }
+}
+
+/// GSettings schemas we read, where installed; missing schemas just leave
+/// the matching preference at its default
+struct DesktopSettings {
+    interface: Option<gio::Settings>,
+    a11y: Option<gio::Settings>,
+    color: Option<gio::Settings>,
+    // gsd's NightLightActive, which follows whichever schedule is set
+    night_light: Cell<bool>,
+}
+
+impl DesktopSettings {
+    fn new() -> Self {
+        Self {
+            interface: settings(INTERFACE_SCHEMA),
+            a11y: settings(A11Y_INTERFACE_SCHEMA),
+            color: settings(COLOR_SCHEMA),
+            night_light: Cell::new(false),
+        }
+    }
+
+    fn appearance(&self) -> Appearance {
+        let light = self
+            .interface
+            .as_ref()
+            .filter(|s| has_key(s, "color-scheme"))
+            .is_some_and(|s| s.string("color-scheme") == "prefer-light");
+
+        // GNOME 43 moved high-contrast out of the theme name into a11y
+        let high_contrast = match (&self.a11y, &self.interface) {
+            (Some(a11y), _) if has_key(a11y, "high-contrast") => a11y.boolean("high-contrast"),
+            (_, Some(interface)) => interface.string("gtk-theme").starts_with("HighContrast"),
+            _ => false,
+        };
+
+        let reduce_motion = self
+            .interface
+            .as_ref()
+            .filter(|s| has_key(s, "enable-animations"))
+            .is_some_and(|s| !s.boolean("enable-animations"));
+
+        let warmth = match &self.color {
+            Some(color) if self.night_light.get() => {
+                let temperature = color.uint("night-light-temperature") as f64;
+                ((NEUTRAL_TEMPERATURE - temperature) / (NEUTRAL_TEMPERATURE - WARMEST_TEMPERATURE)).clamp(0.0, 1.0)
+            }
+            _ => 0.0,
+        };
+
+        Appearance { light, high_contrast, warmth, reduce_motion }
+    }
+}
+
+pub fn settings(schema: &str) -> Option<gio::Settings> {
+    // Settings::new aborts on an unknown schema, so check first
+    gio::SettingsSchemaSource::default()?.lookup(schema, true)?;
+    Some(gio::Settings::new(schema))
+}
+
+pub fn has_key(settings: &gio::Settings, key: &str) -> bool {
+    settings.settings_schema().is_some_and(|schema| schema.has_key(key))
+}
+
+/// Read the desktop appearance and call `on_change` whenever it changes,
+/// for the rest of the process's life
+pub fn watch_appearance<F>(on_change: F) -> Appearance
+where
+    F: Fn(Appearance) + 'static,
+{
+    let desktop = Rc::new(DesktopSettings::new());
+    let initial = desktop.appearance();
+    let current = Cell::new(initial);
+
+    let check = Rc::new({
+        let desktop = desktop.clone();
+        move || {
+            let appearance = desktop.appearance();
+            if current.get() != appearance {
+                current.set(appearance);
+                println!(
+                    "Appearance changed (light: {}, high contrast: {}, warmth: {:.2}, reduce motion: {})",
+                    appearance.light, appearance.high_contrast, appearance.warmth, appearance.reduce_motion
+                );
+                on_change(appearance);
+            }
+        }
+    });
+
+    // Each handler keeps the settings alive through `check`, on purpose
+    for settings in [&desktop.interface, &desktop.a11y, &desktop.color].into_iter().flatten() {
+        let check = check.clone();
+        settings.connect_changed(None, move |_, _| check());
+    }
+    // GSettings only reports keys read after a handler was connected
+    desktop.appearance();
+
+    // Night light turns on and off by schedule without any setting
+    // changing, so follow gsd's own idea of it
+    match gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE) {
+        Ok(connection) => {
+            let on_active: Rc<dyn Fn(bool)> = Rc::new(move |active| {
+                desktop.night_light.set(active);
+                check();
+            });
+            watch_night_light(&connection, on_active);
+        }
+        Err(e) => eprintln!("Cannot follow night light: {}", e),
+    }
+
+    initial
+}
+
+/// Call `on_active` with gsd's NightLightActive now and whenever it
+/// changes, including gsd going away (off) and coming back
+fn watch_night_light(connection: &gio::DBusConnection, on_active: Rc<dyn Fn(bool)>) {
+    let changed = on_active.clone();
+    connection.signal_subscribe(
+        Some(COLOR_BUS_NAME),
+        Some("org.freedesktop.DBus.Properties"),
+        Some("PropertiesChanged"),
+        Some(COLOR_PATH),
+        Some(COLOR_BUS_NAME),
+        gio::DBusSignalFlags::NONE,
+        move |_, _, _, _, _, params| {
+            let Some((_, properties, _)) = params.get::<(String, HashMap<String, Variant>, Vec<String>)>() else { return };
+            if let Some(active) = properties.get("NightLightActive").and_then(|a| a.get::<bool>()) {
+                changed(active);
+            }
+        },
+    );
+
+    let owner = on_active.clone();
+    connection.signal_subscribe(
+        Some("org.freedesktop.DBus"),
+        Some("org.freedesktop.DBus"),
+        Some("NameOwnerChanged"),
+        Some("/org/freedesktop/DBus"),
+        Some(COLOR_BUS_NAME),
+        gio::DBusSignalFlags::NONE,
+        move |connection, _, _, _, _, params| {
+            let Some((_, _, new_owner)) = params.get::<(String, String, String)>() else { return };
+            if new_owner.is_empty() {
+                owner(false);
+            } else {
+                query_night_light(connection, owner.clone());
+            }
+        },
+    );
+
+    query_night_light(connection, on_active);
+}
+
+fn query_night_light(connection: &gio::DBusConnection, on_active: Rc<dyn Fn(bool)>) {
+    connection.call(
+        Some(COLOR_BUS_NAME),
+        COLOR_PATH,
+        "org.freedesktop.DBus.Properties",
+        "Get",
+        Some(&(COLOR_BUS_NAME, "NightLightActive").to_variant()),
+        None,
+        gio::DBusCallFlags::NO_AUTO_START,
+        1000,
+        gio::Cancellable::NONE,
+        move |result| {
+            // Without gsd there is no night light
+            let active = result.ok().and_then(|reply| reply.get::<(Variant,)>()).and_then(|(v,)| v.get::<bool>());
+            on_active(active.unwrap_or(false));
+        },
+    );
+}
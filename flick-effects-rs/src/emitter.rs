@@ -0,0 +1,285 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::gradient::Gradient;
+
+/// Effects compiled into the binary; files in the effects directory with
+/// the same name replace them
+const BUILTIN_EFFECTS: &[(&str, &str)] = &[
+    ("fire.json", include_str!("../effects/fire.json")),
+    ("fireworks.json", include_str!("../effects/fireworks.json")),
+    ("confetti.json", include_str!("../effects/confetti.json")),
+];
+
+/// Name of the effect used when the configured one can't be found
+pub const DEFAULT_EFFECT: &str = "fire";
+
+/// What starts an effect
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    /// Follows a finger from touch down until release
+    #[default]
+    Touch,
+    /// Runs continuously, emitting across the whole screen
+    Ambient,
+    /// Plays once when an app asks for it (D-Bus TriggerEffect or Play)
+    Event,
+}
+
+/// How particles combine with what is already drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Blend {
+    #[default]
+    Over,
+    /// Additive: overlapping particles brighten towards white
+    Add,
+}
+
+/// One effect file: a set of emitters that start and stop together
+#[derive(Clone, Deserialize)]
+pub struct EffectDef {
+    pub name: String,
+    #[serde(default)]
+    pub trigger: Trigger,
+    // Most particles one instance keeps alive, budget permitting
+    #[serde(default = "default_max_particles")]
+    pub max_particles: usize,
+    // Seconds from touch down to full intensity
+    #[serde(default)]
+    pub ramp_up: f64,
+    // Seconds for emission to die out after release
+    #[serde(default)]
+    pub fade_out: f64,
+    // Soft light under the finger while the touch is held
+    #[serde(default)]
+    pub glow: Option<Glow>,
+    // Event effects: where they play unless the caller says otherwise, as
+    // fractions of the screen size, and for how many seconds they emit
+    #[serde(default = "default_origin")]
+    pub origin: [f64; 2],
+    #[serde(default = "default_duration")]
+    pub duration: f64,
+    pub emitters: Vec<EmitterDef>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Glow {
+    pub radius: f64,
+    // Defaults to the middle of the first emitter's gradient
+    #[serde(default)]
+    pub color: Option<[f64; 3]>,
+    pub alpha: f64,
+}
+
+/// Spawning and motion rules for one kind of particle.
+/// `[min, max]` pairs are sampled uniformly per particle.
+#[derive(Clone, Deserialize)]
+pub struct EmitterDef {
+    // Particles per second at full intensity
+    pub rate: f64,
+    // Spawn box around the emitter (width, height), scaled by intensity
+    #[serde(default)]
+    pub spread: [f64; 2],
+    pub speed: [f64; 2],
+    // Launch direction in degrees; 0 is straight up, positive leans right
+    #[serde(default)]
+    pub angle: [f64; 2],
+    // Stretches the launch velocity per axis
+    #[serde(default = "default_pair")]
+    pub velocity_scale: [f64; 2],
+    // Lifetime in seconds
+    pub life: [f64; 2],
+    // Radius in logical pixels
+    pub size: [f64; 2],
+    // Size multiplier over the particle's life (0 = birth, 1 = death)
+    #[serde(default)]
+    pub size_over_life: Curve,
+    #[serde(default)]
+    pub alpha_over_life: Curve,
+    // Acceleration in pixels/s², y pointing down
+    #[serde(default)]
+    pub gravity: [f64; 2],
+    // Fraction of velocity kept per 1/60 s, per axis
+    #[serde(default = "default_pair")]
+    pub drag: [f64; 2],
+    // Random horizontal acceleration
+    #[serde(default)]
+    pub jitter: f64,
+    #[serde(default)]
+    pub turbulence: Turbulence,
+    // Starting position on the gradient, which then drifts down by heat_decay per second
+    #[serde(default = "default_pair")]
+    pub heat: [f64; 2],
+    #[serde(default)]
+    pub heat_decay: f64,
+    // Alpha multiplier by heat, so cooling particles can thin out
+    #[serde(default)]
+    pub alpha_over_heat: Curve,
+    pub gradient: Gradient,
+    #[serde(default)]
+    pub blend: Blend,
+}
+
+/// Sinusoidal sway; the horizontal part grows as particles age
+#[derive(Clone, Default, Deserialize)]
+pub struct Turbulence {
+    // Pixels/s (x, y)
+    pub amplitude: [f64; 2],
+    // Radians/s (x, y)
+    pub frequency: [f64; 2],
+}
+
+/// Piecewise-linear curve through `[t, value]` points; empty means 1.0
+#[derive(Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Curve(Vec<[f64; 2]>);
+
+impl Curve {
+    pub fn sample(&self, t: f64) -> f64 {
+        let points = &self.0;
+        let Some(first) = points.first() else { return 1.0 };
+        if t <= first[0] {
+            return first[1];
+        }
+        for pair in points.windows(2) {
+            let ([t0, v0], [t1, v1]) = (pair[0], pair[1]);
+            if t <= t1 {
+                let span = t1 - t0;
+                return if span > 0.0 { v0 + (v1 - v0) * (t - t0) / span } else { v1 };
+            }
+        }
+        points[points.len() - 1][1]
+    }
+}
+
+fn default_max_particles() -> usize { 50 }
+fn default_pair() -> [f64; 2] { [1.0, 1.0] }
+fn default_origin() -> [f64; 2] { [0.5, 0.4] }
+fn default_duration() -> f64 { 0.5 }
+
+/// Uniform sample from a `[min, max]` pair
+pub fn pick(range: [f64; 2], rng: &mut StdRng) -> f64 {
+    range[0] + rng.gen::<f64>() * (range[1] - range[0])
+}
+
+/// All known effects: the built-ins plus user files
+pub struct EffectLibrary {
+    effects: Vec<EffectDef>,
+}
+
+impl EffectLibrary {
+    pub fn effects_dir() -> PathBuf {
+        let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
+        PathBuf::from(home)
+            .join(".local")
+            .join("share")
+            .join("flick")
+            .join("effects")
+    }
+
+    /// Load the built-ins, then every *.json / *.toml in the effects
+    /// directory. Files that fail to parse are reported and skipped.
+    pub fn load() -> Self {
+        let mut library = Self { effects: Vec::new() };
+
+        for (file, source) in BUILTIN_EFFECTS {
+            match parse_effect(Path::new(file), source) {
+                Ok(effect) => library.insert(effect),
+                Err(e) => eprintln!("Built-in effect {} is invalid: {}", file, e),
+            }
+        }
+
+        let dir = Self::effects_dir();
+        let mut paths: Vec<PathBuf> = match fs::read_dir(&dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(_) => Vec::new(),
+        };
+        paths.sort();
+
+        for path in paths {
+            let ext = path.extension().and_then(|e| e.to_str());
+            if !matches!(ext, Some("json") | Some("toml")) {
+                continue;
+            }
+            let result = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| parse_effect(&path, &source));
+            match result {
+                Ok(effect) => {
+                    println!("Loaded effect '{}' from {:?}", effect.name, path);
+                    library.insert(effect);
+                }
+                Err(e) => eprintln!("Skipping effect {:?}: {}", path, e),
+            }
+        }
+
+        library
+    }
+
+    /// Add an effect, replacing any earlier one with the same name
+    fn insert(&mut self, effect: EffectDef) {
+        match self.effects.iter_mut().find(|e| e.name == effect.name) {
+            Some(existing) => *existing = effect,
+            None => self.effects.push(effect),
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.effects.iter().position(|e| e.name == name)
+    }
+
+    pub fn get(&self, index: usize) -> &EffectDef {
+        &self.effects[index]
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.effects.iter().map(|e| e.name.as_str())
+    }
+
+    /// Recolor every emitter of effect `name`; false if there is no such effect
+    pub fn set_gradient(&mut self, name: &str, gradient: &Gradient) -> bool {
+        let Some(index) = self.find(name) else { return false };
+        for emitter in &mut self.effects[index].emitters {
+            emitter.gradient = gradient.clone();
+        }
+        true
+    }
+
+    /// Move every accent palette to a new theme accent color
+    pub fn set_accent(&mut self, accent: [f64; 3]) {
+        for effect in &mut self.effects {
+            for emitter in &mut effect.emitters {
+                emitter.gradient.set_accent(accent);
+            }
+        }
+    }
+
+    /// Look up the emitter a particle came from
+    pub fn emitter(&self, (effect, emitter): (u16, u16)) -> &EmitterDef {
+        &self.effects[effect as usize].emitters[emitter as usize]
+    }
+}
+
+fn parse_effect(path: &Path, source: &str) -> Result<EffectDef, String> {
+    let effect: EffectDef = if path.extension().is_some_and(|e| e == "toml") {
+        toml::from_str(source).map_err(|e| e.to_string())?
+    } else {
+        serde_json::from_str(source).map_err(|e| e.to_string())?
+    };
+
+    if effect.emitters.is_empty() {
+        return Err("no emitters".to_string());
+    }
+    if effect.emitters.len() > u16::MAX as usize {
+        return Err("too many emitters".to_string());
+    }
+    if effect.emitters.iter().any(|e| e.life[0] <= 0.0 || e.life[1] <= 0.0) {
+        return Err("particle life must be positive".to_string());
+    }
+    Ok(effect)
+}
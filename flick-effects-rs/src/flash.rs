@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+
+use crate::gl_renderer::Sprite;
+
+/// Side of a measuring region in logical pixels: roughly a quarter of a
+/// 10° field of view at phone viewing distance, the area WCAG 2.3.1 uses
+/// for general flashes
+const REGION_SIZE: f64 = 190.0;
+/// Relative luminance change that counts as one transition
+const TRANSITION: f64 = 0.1;
+/// Pairs of transitions whose darker side is above this are not flashes
+const DARK_LIMIT: f64 = 0.8;
+/// Most general flashes (pairs of opposing transitions) in any one second
+const MAX_FLASHES: usize = 3;
+/// Average opacity of a glow sprite over πr²: the solid core plus the fade
+/// out to GLOW_SCALE·r
+const SPRITE_COVERAGE: f64 = 1.29;
+
+/// Flash tracking for one region of the screen
+#[derive(Default)]
+struct Region {
+    // Luminance the overlay would show this frame at full strength
+    luminance: f64,
+    // Scale applied to everything drawn in the region this frame
+    gain: f64,
+    // Lowest shown luminance since the last fall, or highest since the last rise
+    low: f64,
+    high: f64,
+    rising: bool,
+    // When recent transitions happened, in seconds
+    transitions: VecDeque<f64>,
+}
+
+impl Region {
+    /// Record what was shown, counting a transition once it moved far
+    /// enough from the last turning point
+    fn track(&mut self, shown: f64, time: f64) {
+        if self.rising {
+            self.high = self.high.max(shown);
+            if self.high - shown >= TRANSITION {
+                if shown < DARK_LIMIT {
+                    self.transitions.push_back(time);
+                }
+                self.rising = false;
+                self.low = shown;
+            }
+        } else {
+            self.low = self.low.min(shown);
+            if shown - self.low >= TRANSITION {
+                if self.low < DARK_LIMIT {
+                    self.transitions.push_back(time);
+                }
+                self.rising = true;
+                self.high = shown;
+            }
+        }
+    }
+}
+
+/// Photosensitivity safeguard. Estimates how bright the overlay is over
+/// each region of the screen and dims a region rather than let it start a
+/// flash that would exceed three per second (WCAG 2.3.1). Luminance is
+/// measured as if drawn over black, the worst case for a light overlay.
+pub struct FlashLimiter {
+    cols: usize,
+    rows: usize,
+    regions: Vec<Region>,
+    // When a region was last dimmed, to log only the first of a burst
+    limited_at: Option<f64>,
+}
+
+impl FlashLimiter {
+    pub fn new() -> Self {
+        Self {
+            cols: 0,
+            rows: 0,
+            regions: Vec::new(),
+            limited_at: None,
+        }
+    }
+
+    /// Measure this frame's sprites and choose each region's gain.
+    /// `time` is in seconds and only needs to be monotonic.
+    pub fn update(&mut self, batches: &[&[Sprite]], width: f64, height: f64, time: f64) {
+        let cols = (width / REGION_SIZE).ceil().max(1.0) as usize;
+        let rows = (height / REGION_SIZE).ceil().max(1.0) as usize;
+        if (cols, rows) != (self.cols, self.rows) {
+            self.cols = cols;
+            self.rows = rows;
+            self.regions = (0..cols * rows).map(|_| Region::default()).collect();
+        }
+
+        for region in &mut self.regions {
+            region.luminance = 0.0;
+        }
+        let region_area = REGION_SIZE * REGION_SIZE;
+        for sprite in batches.iter().flat_map(|batch| batch.iter()) {
+            let Some(index) = self.index(sprite.x as f64, sprite.y as f64) else { continue };
+            let radius = sprite.radius as f64;
+            let area = (SPRITE_COVERAGE * std::f64::consts::PI * radius * radius).min(region_area);
+            let color = (sprite.r as f64, sprite.g as f64, sprite.b as f64);
+            self.regions[index].luminance += relative_luminance(color) * (sprite.a as f64).clamp(0.0, 1.0) * area / region_area;
+        }
+
+        let mut limited = false;
+        for region in &mut self.regions {
+            while region.transitions.front().is_some_and(|&t| t <= time - 1.0) {
+                region.transitions.pop_front();
+            }
+            let full = region.luminance.min(1.0);
+
+            // A rise always comes back down, so only allow one if the pair
+            // still fits in the last second
+            region.gain = 1.0;
+            let starts_flash = !region.rising && full - region.low >= TRANSITION && region.low < DARK_LIMIT;
+            if starts_flash && region.transitions.len() + 2 > 2 * MAX_FLASHES {
+                region.gain = ((region.low + TRANSITION * 0.9) / full).clamp(0.0, 1.0);
+                limited = true;
+            }
+            region.track(full * region.gain, time);
+        }
+
+        if limited {
+            if !matches!(self.limited_at, Some(t) if time - t <= 1.0) {
+                println!("Flash limiter dimming effects");
+            }
+            self.limited_at = Some(time);
+        }
+    }
+
+    /// Alpha multiplier for anything drawn at (x, y) this frame
+    pub fn gain(&self, x: f64, y: f64) -> f64 {
+        self.index(x, y).map_or(1.0, |i| self.regions[i].gain)
+    }
+
+    fn index(&self, x: f64, y: f64) -> Option<usize> {
+        if self.regions.is_empty() {
+            return None;
+        }
+        let col = ((x / REGION_SIZE).max(0.0) as usize).min(self.cols - 1);
+        let row = ((y / REGION_SIZE).max(0.0) as usize).min(self.rows - 1);
+        Some(row * self.cols + col)
+    }
+}
+
+impl Default for FlashLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// WCAG relative luminance of an sRGB color
+fn relative_luminance((r, g, b): (f64, f64, f64)) -> f64 {
+    let linear = |c: f64| {
+        let c = c.clamp(0.0, 1.0);
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprites::SpriteCache;
+    use gtk4::cairo::{Context, Format, ImageSurface, Operator};
+
+    const WIDTH: i32 = 540;
+    const HEIGHT: i32 = 1170;
+    const FPS: f64 = 60.0;
+    const SECONDS: f64 = 3.0;
+
+    /// A large white glow switching every 3 frames, and a small orange one
+    /// breathing once a second
+    fn scene(time: f64) -> Vec<Sprite> {
+        let mut sprites = Vec::new();
+        if (time * 20.0) as i64 % 2 == 0 {
+            sprites.push(Sprite::new(270.0, 400.0, 80.0, 1.0, 1.0, 1.0, 1.0));
+        }
+        let breath = 0.5 + 0.5 * (time * 2.0 * std::f64::consts::PI).sin();
+        sprites.push(Sprite::new(100.0, 1000.0, 20.0, 1.0, 0.5, 0.1, breath));
+        sprites
+    }
+
+    impl FlashLimiter {
+        /// Track transitions from the real pixels of `surface`, composited
+        /// over black, instead of estimating from sprites
+        fn measure_pixels(&mut self, surface: &mut ImageSurface, time: f64) {
+            let (width, height) = (surface.width() as usize, surface.height() as usize);
+            let stride = surface.stride() as usize;
+            self.cols = (width as f64 / REGION_SIZE).ceil() as usize;
+            self.rows = (height as f64 / REGION_SIZE).ceil() as usize;
+            if self.regions.len() != self.cols * self.rows {
+                self.regions = (0..self.cols * self.rows).map(|_| Region::default()).collect();
+            }
+
+            let mut sums = vec![0.0; self.regions.len()];
+            let data = surface.data().expect("test surface is borrowed");
+            for y in 0..height {
+                for x in 0..width {
+                    // Premultiplied BGRA, which over black is just the color
+                    let p = &data[y * stride + x * 4..];
+                    let color = (p[2] as f64 / 255.0, p[1] as f64 / 255.0, p[0] as f64 / 255.0);
+                    let index = self.index(x as f64, y as f64).expect("regions were sized above");
+                    sums[index] += relative_luminance(color);
+                }
+            }
+
+            let region_area = REGION_SIZE * REGION_SIZE;
+            for (region, sum) in self.regions.iter_mut().zip(sums) {
+                while region.transitions.front().is_some_and(|&t| t <= time - 1.0) {
+                    region.transitions.pop_front();
+                }
+                region.track((sum / region_area).min(1.0), time);
+            }
+        }
+    }
+
+    /// Render the scene offscreen, with or without the limiter, and return
+    /// the most flashes counted from the pixels in any one second
+    fn worst_flashes(limit: bool) -> usize {
+        let mut surface = ImageSurface::create(Format::ARgb32, WIDTH, HEIGHT).expect("Failed to create test surface");
+        let mut cache = SpriteCache::new();
+        let mut limiter = FlashLimiter::new();
+        // Independent flash counter fed from the rendered pixels
+        let mut measured = FlashLimiter::new();
+        let mut worst = 0;
+
+        for frame in 0..(SECONDS * FPS) as usize {
+            let time = frame as f64 / FPS;
+            let mut sprites = scene(time);
+            if limit {
+                limiter.update(&[&sprites], WIDTH as f64, HEIGHT as f64, time);
+                for sprite in &mut sprites {
+                    sprite.a *= limiter.gain(sprite.x as f64, sprite.y as f64) as f32;
+                }
+            }
+
+            {
+                let cr = Context::new(&surface).expect("Failed to create test context");
+                cr.set_operator(Operator::Clear);
+                cr.paint().ok();
+                cr.set_operator(Operator::Over);
+                for s in &sprites {
+                    let color = (s.r as f64, s.g as f64, s.b as f64);
+                    cache.draw(&cr, s.x as f64, s.y as f64, s.radius as f64, color, s.a as f64);
+                }
+            }
+            surface.flush();
+
+            measured.measure_pixels(&mut surface, time);
+            let flashes = measured.regions.iter().map(|r| r.transitions.len() / 2).max().unwrap_or(0);
+            worst = worst.max(flashes);
+        }
+        worst
+    }
+
+    #[test]
+    fn limiter_keeps_strobe_within_limit() {
+        let worst = worst_flashes(true);
+        assert!(worst <= MAX_FLASHES, "{} flashes in a second with the limiter", worst);
+    }
+
+    #[test]
+    fn strobe_flashes_too_often_unlimited() {
+        let worst = worst_flashes(false);
+        assert!(worst > MAX_FLASHES, "only {} flashes in a second without the limiter", worst);
+    }
+}
@@ -0,0 +1,268 @@
+use khronos_egl as egl;
+use crate::emitter::Blend;
+use std::ffi::CString;
+use std::mem::size_of;
+use std::ptr;
+
+/// One glowing particle, uploaded as per-instance vertex data
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Sprite {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Sprite {
+    pub fn new(x: f64, y: f64, radius: f64, r: f64, g: f64, b: f64, a: f64) -> Self {
+        Self {
+            x: x as f32,
+            y: y as f32,
+            radius: radius as f32,
+            r: r as f32,
+            g: g as f32,
+            b: b as f32,
+            a: a as f32,
+        }
+    }
+}
+
+const VERTEX_SHADER: &str = r#"#version 300 es
+layout(location = 0) in vec2 a_corner;
+layout(location = 1) in vec3 a_sprite;
+layout(location = 2) in vec4 a_color;
+uniform vec2 u_resolution;
+out vec2 v_uv;
+out vec4 v_color;
+
+// Quads extend past the particle radius to leave room for the glow
+const float GLOW_SCALE = 2.0;
+
+void main() {
+    vec2 pos = a_sprite.xy + a_corner * a_sprite.z * GLOW_SCALE;
+    vec2 ndc = pos / u_resolution * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+    v_uv = a_corner * GLOW_SCALE;
+    v_color = a_color;
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 300 es
+precision mediump float;
+in vec2 v_uv;
+in vec4 v_color;
+out vec4 frag_color;
+
+void main() {
+    // d == 1.0 at the particle radius: solid core, soft falloff beyond it
+    float d = length(v_uv);
+    float core = 1.0 - smoothstep(0.8, 1.0, d);
+    float glow = exp(-d * d * 1.5) * 0.5;
+    float a = v_color.a * max(core, glow);
+    frag_color = vec4(v_color.rgb * a, a);
+}
+"#;
+
+/// Instanced point-sprite renderer for a GtkGLArea (GLES 3.0).
+/// Must be created and used with the area's context current.
+pub struct GlRenderer {
+    program: u32,
+    vao: u32,
+    quad_vbo: u32,
+    instance_vbo: u32,
+    u_resolution: i32,
+    // Instance buffer size in sprites, grown on demand
+    capacity: usize,
+    // Keeps libEGL loaded while the GL function pointers are in use
+    _egl: egl::DynamicInstance<egl::EGL1_4>,
+}
+
+impl GlRenderer {
+    pub fn new() -> Result<Self, String> {
+        let egl = load_gl_functions()?;
+
+        unsafe {
+            let program = link_program(VERTEX_SHADER, FRAGMENT_SHADER)?;
+            let name = CString::new("u_resolution").unwrap();
+            let u_resolution = gl::GetUniformLocation(program, name.as_ptr());
+
+            let mut vao = 0;
+            let mut buffers = [0u32; 2];
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(2, buffers.as_mut_ptr());
+            let [quad_vbo, instance_vbo] = buffers;
+
+            gl::BindVertexArray(vao);
+
+            // Shared unit quad, drawn as a triangle strip
+            let corners: [f32; 8] = [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
+            gl::BindBuffer(gl::ARRAY_BUFFER, quad_vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                size_of::<[f32; 8]>() as isize,
+                corners.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, ptr::null());
+
+            // Per-instance position/radius and color
+            let stride = size_of::<Sprite>() as i32;
+            gl::BindBuffer(gl::ARRAY_BUFFER, instance_vbo);
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl::VertexAttribDivisor(1, 1);
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(2, 4, gl::FLOAT, gl::FALSE, stride, (3 * size_of::<f32>()) as *const _);
+            gl::VertexAttribDivisor(2, 1);
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            Ok(Self {
+                program,
+                vao,
+                quad_vbo,
+                instance_vbo,
+                u_resolution,
+                capacity: 0,
+                _egl: egl,
+            })
+        }
+    }
+
+    /// Clear the area and draw each batch of sprites in order with its
+    /// blend mode; coordinates are logical pixels
+    pub fn render(&mut self, batches: &[(Blend, &[Sprite])], width: i32, height: i32) {
+        unsafe {
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            if batches.iter().all(|(_, sprites)| sprites.is_empty()) {
+                return;
+            }
+
+            gl::UseProgram(self.program);
+            gl::Uniform2f(self.u_resolution, width.max(1) as f32, height.max(1) as f32);
+            gl::Enable(gl::BLEND);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_vbo);
+
+            for &(blend, sprites) in batches {
+                if sprites.is_empty() {
+                    continue;
+                }
+
+                // GTK composites premultiplied alpha
+                match blend {
+                    Blend::Over => gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+                    Blend::Add => gl::BlendFunc(gl::ONE, gl::ONE),
+                }
+
+                let bytes = std::mem::size_of_val(sprites) as isize;
+                if sprites.len() > self.capacity {
+                    self.capacity = sprites.len().next_power_of_two();
+                    gl::BufferData(
+                        gl::ARRAY_BUFFER,
+                        (self.capacity * size_of::<Sprite>()) as isize,
+                        ptr::null(),
+                        gl::STREAM_DRAW,
+                    );
+                }
+                gl::BufferSubData(gl::ARRAY_BUFFER, 0, bytes, sprites.as_ptr() as *const _);
+
+                gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, sprites.len() as i32);
+            }
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+            gl::UseProgram(0);
+            gl::Disable(gl::BLEND);
+        }
+    }
+}
+
+impl Drop for GlRenderer {
+    // The owning GLArea's context must be current (done in its unrealize handler)
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(2, [self.quad_vbo, self.instance_vbo].as_ptr());
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
+
+/// Resolve GL entry points through EGL, which GTK uses on Wayland
+fn load_gl_functions() -> Result<egl::DynamicInstance<egl::EGL1_4>, String> {
+    let egl = unsafe { egl::DynamicInstance::<egl::EGL1_4>::load_required() }
+        .map_err(|e| format!("Cannot load libEGL: {}", e))?;
+
+    gl::load_with(|name| {
+        egl.get_proc_address(name)
+            .map_or(ptr::null(), |f| f as *const std::ffi::c_void)
+    });
+
+    if !gl::DrawArraysInstanced::is_loaded() || !gl::VertexAttribDivisor::is_loaded() {
+        return Err("GL driver lacks instanced drawing".to_string());
+    }
+    Ok(egl)
+}
+
+unsafe fn compile_shader(kind: u32, source: &str) -> Result<u32, String> {
+    let shader = gl::CreateShader(kind);
+    let source = CString::new(source).unwrap();
+    gl::ShaderSource(shader, 1, &source.as_ptr(), ptr::null());
+    gl::CompileShader(shader);
+
+    let mut ok = 0;
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut ok);
+    if ok == 0 {
+        let log = info_log(shader, gl::GetShaderiv, gl::GetShaderInfoLog);
+        gl::DeleteShader(shader);
+        return Err(format!("Shader compile failed: {}", log));
+    }
+    Ok(shader)
+}
+
+unsafe fn link_program(vertex: &str, fragment: &str) -> Result<u32, String> {
+    let vs = compile_shader(gl::VERTEX_SHADER, vertex)?;
+    let fs = match compile_shader(gl::FRAGMENT_SHADER, fragment) {
+        Ok(fs) => fs,
+        Err(e) => {
+            gl::DeleteShader(vs);
+            return Err(e);
+        }
+    };
+
+    let program = gl::CreateProgram();
+    gl::AttachShader(program, vs);
+    gl::AttachShader(program, fs);
+    gl::LinkProgram(program);
+    gl::DeleteShader(vs);
+    gl::DeleteShader(fs);
+
+    let mut ok = 0;
+    gl::GetProgramiv(program, gl::LINK_STATUS, &mut ok);
+    if ok == 0 {
+        let log = info_log(program, gl::GetProgramiv, gl::GetProgramInfoLog);
+        gl::DeleteProgram(program);
+        return Err(format!("Shader link failed: {}", log));
+    }
+    Ok(program)
+}
+
+unsafe fn info_log(
+    object: u32,
+    get_iv: unsafe fn(u32, u32, *mut i32),
+    get_log: unsafe fn(u32, i32, *mut i32, *mut gl::types::GLchar),
+) -> String {
+    let mut len = 0;
+    get_iv(object, gl::INFO_LOG_LENGTH, &mut len);
+    let mut buf = vec![0u8; len.max(1) as usize];
+    get_log(object, len, ptr::null_mut(), buf.as_mut_ptr() as *mut _);
+    String::from_utf8_lossy(&buf).trim_end_matches('\0').to_string()
+}
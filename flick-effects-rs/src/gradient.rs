@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+/// Flick's default theme accent, used by the "accent" palette
+pub const DEFAULT_ACCENT: [f64; 3] = [0.914, 0.271, 0.376];
+
+/// How colors between two stops are mixed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpolation {
+    /// Straight blend of the sRGB values
+    #[default]
+    Linear,
+    /// Perceptually even blend; keeps brightness and saturation steadier
+    Oklab,
+}
+
+/// Color ramp sampled at 0..1, built from stops or a named palette.
+///
+/// Accepted forms, in config and effect files:
+/// `"plasma"`, `[[0.0, [r, g, b]], ...]`, or
+/// `{ "palette": "rainbow" | "stops": [...], "interpolation": "oklab" }`.
+/// Repeating a stop position gives a hard edge.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "GradientSpec", into = "GradientSpec")]
+pub struct Gradient {
+    stops: Vec<(f64, [f64; 3])>,
+    interpolation: Interpolation,
+    // Stops converted to OKLab when interpolating there
+    lab: Vec<[f64; 3]>,
+    // Named palette this came from, if any
+    palette: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum GradientSpec {
+    Palette(String),
+    Stops(Vec<(f64, [f64; 3])>),
+    Full {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        palette: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        stops: Vec<(f64, [f64; 3])>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        interpolation: Option<Interpolation>,
+    },
+}
+
+impl TryFrom<GradientSpec> for Gradient {
+    type Error = String;
+
+    fn try_from(spec: GradientSpec) -> Result<Self, String> {
+        match spec {
+            GradientSpec::Palette(name) => Gradient::palette(&name),
+            GradientSpec::Stops(stops) => Ok(Gradient::new(stops, Interpolation::Linear)),
+            GradientSpec::Full { palette, stops, interpolation } => {
+                let mut gradient = match palette {
+                    Some(name) => Gradient::palette(&name)?,
+                    None if !stops.is_empty() => Gradient::new(stops, Interpolation::Linear),
+                    None => return Err("gradient needs a palette or stops".to_string()),
+                };
+                if let Some(interpolation) = interpolation {
+                    gradient.set_interpolation(interpolation);
+                }
+                Ok(gradient)
+            }
+        }
+    }
+}
+
+impl From<Gradient> for GradientSpec {
+    fn from(gradient: Gradient) -> Self {
+        let stops = if gradient.palette.is_some() { Vec::new() } else { gradient.stops };
+        GradientSpec::Full {
+            palette: gradient.palette,
+            stops,
+            interpolation: Some(gradient.interpolation),
+        }
+    }
+}
+
+impl Gradient {
+    pub fn new(mut stops: Vec<(f64, [f64; 3])>, interpolation: Interpolation) -> Self {
+        // Stable, so repeated positions keep their order
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut gradient = Self {
+            stops,
+            interpolation,
+            lab: Vec::new(),
+            palette: None,
+        };
+        gradient.set_interpolation(interpolation);
+        gradient
+    }
+
+    /// Built-in palettes: fire, blue_flame, plasma, rainbow, plus accent,
+    /// accent_fire, accent_glow and accent_star, which follow the theme
+    /// accent color (see set_accent)
+    pub fn palette(name: &str) -> Result<Self, String> {
+        use Interpolation::*;
+        let mut gradient = match name {
+            "fire" => Gradient::new(vec![
+                (0.1, [0.2, 0.2, 0.2]),
+                (0.1, [0.5, 0.05, 0.02]),
+                (0.3, [0.9, 0.2, 0.05]),
+                (0.5, [1.0, 0.5, 0.1]),
+                (0.7, [1.0, 0.9, 0.3]),
+                (0.9, [1.0, 1.0, 0.9]),
+            ], Linear),
+            "blue_flame" => Gradient::new(vec![
+                (0.1, [0.15, 0.15, 0.2]),
+                (0.1, [0.05, 0.05, 0.35]),
+                (0.3, [0.1, 0.2, 0.8]),
+                (0.5, [0.2, 0.5, 1.0]),
+                (0.7, [0.5, 0.8, 1.0]),
+                (0.9, [0.9, 0.95, 1.0]),
+            ], Linear),
+            "plasma" => Gradient::new(vec![
+                (0.0, [0.05, 0.03, 0.53]),
+                (0.25, [0.49, 0.01, 0.66]),
+                (0.5, [0.8, 0.28, 0.47]),
+                (0.75, [0.97, 0.59, 0.25]),
+                (1.0, [0.94, 0.98, 0.13]),
+            ], Oklab),
+            "rainbow" => Gradient::new(vec![
+                (0.0, [1.0, 0.0, 0.0]),
+                (1.0 / 6.0, [1.0, 1.0, 0.0]),
+                (2.0 / 6.0, [0.0, 1.0, 0.0]),
+                (3.0 / 6.0, [0.0, 1.0, 1.0]),
+                (4.0 / 6.0, [0.0, 0.0, 1.0]),
+                (5.0 / 6.0, [1.0, 0.0, 1.0]),
+                (1.0, [1.0, 0.0, 0.0]),
+            ], Linear),
+            _ => match accent_palette(name, DEFAULT_ACCENT) {
+                Some(gradient) => gradient,
+                None => return Err(format!("unknown palette '{}'", name)),
+            },
+        };
+        gradient.palette = Some(name.to_string());
+        Ok(gradient)
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+        self.lab = match interpolation {
+            Interpolation::Linear => Vec::new(),
+            Interpolation::Oklab => self.stops.iter().map(|&(_, c)| srgb_to_oklab(c)).collect(),
+        };
+    }
+
+    /// Rebuild an accent palette around a new theme color; other
+    /// gradients are left alone
+    pub fn set_accent(&mut self, accent: [f64; 3]) {
+        let Some(name) = self.palette.take() else { return };
+        if let Some(gradient) = accent_palette(&name, accent) {
+            let interpolation = self.interpolation;
+            *self = gradient;
+            self.set_interpolation(interpolation);
+        }
+        self.palette = Some(name);
+    }
+
+    pub fn sample(&self, t: f64) -> (f64, f64, f64) {
+        let stops = &self.stops;
+        let Some(first) = stops.first() else { return (1.0, 1.0, 1.0) };
+        if t <= first.0 {
+            return tuple(first.1);
+        }
+
+        for (i, pair) in stops.windows(2).enumerate() {
+            let ((t0, c0), (t1, c1)) = (pair[0], pair[1]);
+            if t < t1 {
+                let f = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1.0 };
+                let color = match self.interpolation {
+                    Interpolation::Linear => lerp(c0, c1, f),
+                    Interpolation::Oklab => oklab_to_srgb(lerp(self.lab[i], self.lab[i + 1], f)),
+                };
+                return tuple(color);
+            }
+        }
+        tuple(stops[stops.len() - 1].1)
+    }
+}
+
+/// Palettes derived from the theme accent `a`
+fn accent_palette(name: &str, a: [f64; 3]) -> Option<Gradient> {
+    let white = [1.0, 1.0, 1.0];
+    let stops = match name {
+        // Dark shade to the accent to a pale tint
+        "accent" => vec![
+            (0.0, a.map(|c| c * 0.25)),
+            (0.6, a),
+            (1.0, lerp(a, white, 0.8)),
+        ],
+        // Same banding as "fire", with the accent as the flame color
+        "accent_fire" => vec![
+            (0.1, [0.2, 0.2, 0.2]),
+            (0.1, a.map(|c| c * 0.35)),
+            (0.3, a.map(|c| c * 0.8)),
+            (0.5, a),
+            (0.7, lerp(a, white, 0.5)),
+            (0.9, lerp(a, white, 0.9)),
+        ],
+        "accent_glow" => vec![(0.0, a), (1.0, lerp(a, white, 0.5))],
+        // Near-white with a hint of the accent
+        "accent_star" => vec![(0.0, lerp(white, a, 0.15)), (1.0, lerp(white, a, 0.35))],
+        _ => return None,
+    };
+    Some(Gradient::new(stops, Interpolation::Oklab))
+}
+
+fn lerp(a: [f64; 3], b: [f64; 3], f: f64) -> [f64; 3] {
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * f)
+}
+
+fn tuple(c: [f64; 3]) -> (f64, f64, f64) {
+    (c[0], c[1], c[2])
+}
+
+// ============ OKLAB ============
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+pub fn srgb_to_oklab(rgb: [f64; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+pub fn oklab_to_srgb([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    [
+        4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_,
+        -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_,
+        -0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_,
+    ]
+    .map(linear_to_srgb)
+}
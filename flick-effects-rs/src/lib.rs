@@ -0,0 +1,26 @@
+//! The effects, their simulation and the overlay's system integration,
+//! shared by the flick-effects binary, its integration tests and benches
+
+pub mod touch;
+pub mod config;
+pub mod capture;
+pub mod replay;
+pub mod gl_renderer;
+pub mod bounds;
+pub mod canvas;
+pub mod sprites;
+pub mod particles;
+pub mod quality;
+pub mod emitter;
+pub mod script;
+pub mod gradient;
+pub mod theme;
+pub mod desktop;
+pub mod flash;
+pub mod dbus;
+pub mod oneshot;
+pub mod notifications;
+pub mod calls;
+pub mod mpris;
+pub mod audio;
+pub mod visualizer;
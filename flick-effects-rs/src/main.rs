@@ -1,140 +1,252 @@
 use gtk4::prelude::*;
-use gtk4::{Application, ApplicationWindow, DrawingArea};
+use gtk4::{Application, ApplicationWindow, GLArea};
+use gtk4::cairo::Operator;
 use gtk4_layer_shell::{Edge, Layer, LayerShell};
 use gdk4::prelude::SurfaceExt;
 use glib::ControlFlow;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
-use std::sync::mpsc;
-use std::time::Instant;
 use std::f64::consts::PI;
-use rand::Rng;
-
-mod touch;
-mod config;
-mod capture;
-
-use touch::TouchMonitor;
-use config::Config;
+use std::time::Instant;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 
-// ============ FIRE PARTICLE ============
+mod args;
 
-#[derive(Clone)]
-struct FireParticle {
-    x: f64,
-    y: f64,
-    vx: f64,
-    vy: f64,
-    life: f64,
-    max_life: f64,
-    size: f64,
-    heat: f64,
-    wobble: f64,
-}
+use flick_effects::{
+    audio, bounds, calls, canvas, config, dbus, desktop, emitter, flash, gl_renderer, gradient, mpris, notifications,
+    oneshot, particles, quality, replay, script, sprites, theme, touch, visualizer,
+};
 
-// ============ FIRE EFFECT ============
-
-struct FireEffect {
+use touch::TouchMonitor;
+use config::{AppGlow, Config, Renderer, Visualizer};
+use args::Args;
+use replay::{Recorder, Replay};
+use gl_renderer::{GlRenderer, Sprite};
+use bounds::Bounds;
+use canvas::{EffectsCanvas, Layers};
+use sprites::{SpriteCache, GLOW_SCALE};
+use particles::{fair_shares, ParticlePool, Spawn};
+use quality::Quality;
+use emitter::{pick, Blend, EffectDef, EffectLibrary, Trigger, DEFAULT_EFFECT};
+use script::{DrawCommand, Frame, ScriptHost, Shape};
+use gradient::{Gradient, Interpolation};
+use desktop::Appearance;
+use flash::FlashLimiter;
+use dbus::{ClientPolicy, PlayParams, Status, TriggerParams};
+use oneshot::{OneShot, OneShotKind};
+use notifications::{Notification, NotificationWatcher};
+use calls::CallWatcher;
+use mpris::{MprisWatcher, NowPlaying};
+use audio::{AudioAnalyzer, AudioLevels};
+use visualizer::Spectrum;
+
+// ============ EFFECTS ============
+
+/// Reduced motion: radius of the dot shown under the finger
+const STILL_DOT_RADIUS: f64 = 12.0;
+const STILL_DOT_ALPHA: f64 = 0.35;
+/// Reduced motion: seconds for the dot to fade after release
+const STILL_FADE: f64 = 0.25;
+
+/// One running copy of an effect from the library: a touch being tracked,
+/// or an ambient effect covering the screen
+struct EffectInstance {
+    // Tags this instance's particles in the shared pool
+    id: u32,
+    // Index into the effect library
+    effect: usize,
+    ambient: bool,
     x: f64,
     y: f64,
-    particles: Vec<FireParticle>,
     is_active: bool,
     intensity: f64,
-    start_time: Instant,
+    // Simulation time since the effect started (not wall-clock, so replays match)
+    age: f64,
+    // Share of the global particle budget, updated every tick
+    quota: usize,
+    // Fractional particles owed to each emitter, carried between ticks
+    spawn_debt: Vec<f64>,
+    // Reduced motion: show a dot instead of emitting
+    still: bool,
+    // Age at which a triggered effect lets go, as if the finger lifted;
+    // None for touch-driven and ambient effects
+    release_at: Option<f64>,
 }
 
-impl FireEffect {
-    fn new(x: f64, y: f64) -> Self {
+impl EffectInstance {
+    fn new(id: u32, effect: usize, def: &EffectDef, x: f64, y: f64) -> Self {
         Self {
+            id,
+            effect,
+            ambient: def.trigger == Trigger::Ambient,
             x, y,
-            particles: Vec::with_capacity(150),
             is_active: true,
             intensity: 0.0,
-            start_time: Instant::now(),
+            age: 0.0,
+            quota: def.max_particles,
+            spawn_debt: vec![0.0; def.emitters.len()],
+            still: false,
+            release_at: None,
         }
     }
 
-    fn spawn_particles(&mut self, dt: f64) {
+    /// `rate` scales how many particles are emitted per second;
+    /// ambient effects spawn anywhere within `width` x `height`
+    fn spawn_particles(
+        &mut self,
+        def: &EffectDef,
+        dt: f64,
+        rate: f64,
+        (width, height): (f64, f64),
+        rng: &mut StdRng,
+        pool: &mut ParticlePool,
+    ) {
+        self.age += dt;
         if !self.is_active { return; }
-        if self.particles.len() > 50 { return; }
-
-        let mut rng = rand::thread_rng();
-        self.intensity = (self.start_time.elapsed().as_secs_f64() * 8.0).min(1.0);
-        let spawn_count = (15.0 * self.intensity * dt * 60.0) as i32;
-
-        for _ in 0..spawn_count {
-            let spread = 25.0 * self.intensity;
-            let offset_x = (rng.gen::<f64>() - 0.5) * spread;
-            let offset_y = (rng.gen::<f64>() - 0.5) * spread * 0.5;
-            let speed = rng.gen::<f64>() * 250.0 + 150.0;
-            let angle = -PI/2.0 + (rng.gen::<f64>() - 0.5) * 0.8;
-            let heat = rng.gen::<f64>() * 0.3 + 0.7;
-            let max_life = rng.gen::<f64>() * 0.6 + 0.3;
-
-            self.particles.push(FireParticle {
-                x: self.x + offset_x,
-                y: self.y + offset_y,
-                vx: angle.cos() * speed * 0.3,
-                vy: angle.sin() * speed,
-                life: 1.0,
-                max_life,
-                size: rng.gen::<f64>() * 15.0 + 8.0,
-                heat,
-                wobble: rng.gen::<f64>() * PI * 2.0,
-            });
 
-            // Sparks
-            if rng.gen::<f64>() < 0.1 * self.intensity {
-                let spark_angle = -PI/2.0 + (rng.gen::<f64>() - 0.5) * 1.5;
-                let spark_speed = rng.gen::<f64>() * 300.0 + 150.0;
-                self.particles.push(FireParticle {
-                    x: self.x + (rng.gen::<f64>() - 0.5) * 20.0,
-                    y: self.y,
-                    vx: spark_angle.cos() * spark_speed,
-                    vy: spark_angle.sin() * spark_speed,
-                    life: 1.0,
-                    max_life: rng.gen::<f64>() * 0.3 + 0.1,
-                    size: rng.gen::<f64>() * 3.0 + 1.0,
-                    heat: 1.0,
+        self.intensity = if def.ramp_up > 0.0 { (self.age / def.ramp_up).min(1.0) } else { 1.0 };
+        // Whatever the quota kept back last frame is not owed any more: keep
+        // at most this frame's particles plus the fraction left over, so a
+        // full effect doesn't burst out a backlog once particles free up
+        for (debt, emitter) in self.spawn_debt.iter_mut().zip(&def.emitters) {
+            let owed = emitter.rate * self.intensity * rate * dt;
+            *debt = (*debt + owed).min(owed + 1.0);
+        }
+
+        // Take turns between emitters so a busy one can't use up the whole quota
+        let mut count = pool.count(self.id);
+        let mut spawned_any = true;
+        while spawned_any {
+            spawned_any = false;
+            for (index, emitter) in def.emitters.iter().enumerate() {
+                if self.spawn_debt[index] < 1.0 { continue; }
+                if count >= self.quota { return; }
+
+                let (x, y) = if self.ambient {
+                    (rng.gen::<f64>() * width, rng.gen::<f64>() * height)
+                } else {
+                    (self.x, self.y)
+                };
+                let angle = pick(emitter.angle, rng).to_radians();
+                let speed = pick(emitter.speed, rng);
+
+                let spawned = pool.push(self.id, Spawn {
+                    source: (self.effect as u16, index as u16),
+                    x: x + (rng.gen::<f64>() - 0.5) * emitter.spread[0] * self.intensity,
+                    y: y + (rng.gen::<f64>() - 0.5) * emitter.spread[1] * self.intensity,
+                    vx: angle.sin() * speed * emitter.velocity_scale[0],
+                    vy: -angle.cos() * speed * emitter.velocity_scale[1],
+                    max_life: pick(emitter.life, rng),
+                    size: pick(emitter.size, rng),
+                    heat: pick(emitter.heat, rng),
                     wobble: rng.gen::<f64>() * PI * 2.0,
                 });
+                if !spawned { return; }
+                self.spawn_debt[index] -= 1.0;
+                count += 1;
+                spawned_any = true;
             }
         }
     }
 
-    fn update(&mut self, dt: f64, time: f64) {
-        let mut rng = rand::thread_rng();
+    fn update(&mut self, def: &EffectDef, dt: f64) {
+        if !self.is_active {
+            let fade = if def.fade_out > 0.0 { dt / def.fade_out } else { 1.0 };
+            self.intensity = (self.intensity - fade).max(0.0);
+        }
+    }
+
+    /// Reduced motion stand-in for spawn_particles and update: the dot
+    /// appears at full strength and fades quickly after release
+    fn update_still(&mut self, dt: f64) {
+        self.age += dt;
+        self.intensity = if self.is_active { 1.0 } else { (self.intensity - dt / STILL_FADE).max(0.0) };
+    }
 
-        for p in &mut self.particles {
-            let turb_x = (p.wobble + time * 8.0).sin() * 30.0 * (1.0 - p.life);
-            let turb_y = (p.wobble * 1.3 + time * 6.0).cos() * 15.0;
+    /// Whether touches move and release this instance
+    fn follows_touch(&self) -> bool {
+        self.is_active && !self.ambient && self.release_at.is_none()
+    }
 
-            p.x += (p.vx + turb_x) * dt;
-            p.y += (p.vy + turb_y) * dt;
-            p.vx *= 0.98;
-            p.vy -= 80.0 * dt;
-            p.vx += (rng.gen::<f64>() - 0.5) * 100.0 * dt;
-            p.life -= dt / p.max_life;
-            p.heat = (p.heat - dt * 0.8).max(0.0);
+    fn is_done(&self, pool: &ParticlePool) -> bool {
+        !self.is_active && self.intensity <= 0.0 && pool.count(self.id) == 0
+    }
 
-            let life_phase = 1.0 - p.life;
-            if life_phase < 0.2 {
-                p.size *= 1.0 + dt * 2.0;
-            } else {
-                p.size *= 1.0 - dt * 0.5;
+    /// Glow under the finger as (radius, color, alpha), if showing;
+    /// with reduced motion, the dot that replaces the particles
+    fn glow(&self, def: &EffectDef) -> Option<(f64, (f64, f64, f64), f64)> {
+        let color = match def.glow.as_ref().and_then(|g| g.color) {
+            Some([r, g, b]) => (r, g, b),
+            None => def.emitters[0].gradient.sample(0.5),
+        };
+        if self.still {
+            if self.ambient || self.intensity <= 0.0 {
+                return None;
             }
+            return Some((STILL_DOT_RADIUS, color, STILL_DOT_ALPHA * self.intensity));
         }
 
-        self.particles.retain(|p| p.life > 0.0 && p.size > 0.5);
+        let glow = def.glow.as_ref()?;
+        if self.ambient || !self.is_active || self.intensity <= 0.3 {
+            return None;
+        }
+        Some((glow.radius * self.intensity, color, glow.alpha * self.intensity))
+    }
 
-        if !self.is_active {
-            self.intensity = (self.intensity - dt * 3.0).max(0.0);
+    /// Screen area covered by the particles and the glow
+    fn bounds(&self, def: &EffectDef, pool: &ParticlePool) -> Bounds {
+        let mut bounds = Bounds::empty();
+        for i in pool.owned_by(self.id) {
+            bounds.include(pool.x[i], pool.y[i], pool.size[i] * GLOW_SCALE);
         }
+        if let Some((radius, _, _)) = self.glow(def) {
+            bounds.include(self.x, self.y, radius * GLOW_SCALE);
+        }
+        bounds
     }
+}
 
-    fn is_done(&self) -> bool {
-        !self.is_active && self.particles.is_empty()
+/// Advance every particle in the pool by its emitter's rules and cull the dead ones
+fn update_particles(pool: &mut ParticlePool, library: &EffectLibrary, dt: f64, time: f64, rng: &mut StdRng) {
+    for i in 0..pool.len() {
+        let emitter = library.emitter(pool.source[i]);
+        let turbulence = &emitter.turbulence;
+        let life = pool.life[i];
+        let wobble = pool.wobble[i];
+        let turb_x = (wobble + time * turbulence.frequency[0]).sin() * turbulence.amplitude[0] * (1.0 - life);
+        let turb_y = (wobble * 1.3 + time * turbulence.frequency[1]).cos() * turbulence.amplitude[1];
+
+        pool.x[i] += (pool.vx[i] + turb_x) * dt;
+        pool.y[i] += (pool.vy[i] + turb_y) * dt;
+        pool.vx[i] *= emitter.drag[0].powf(dt * 60.0);
+        pool.vy[i] *= emitter.drag[1].powf(dt * 60.0);
+        pool.vx[i] += emitter.gravity[0] * dt + (rng.gen::<f64>() - 0.5) * emitter.jitter * dt;
+        pool.vy[i] += emitter.gravity[1] * dt;
+        pool.life[i] -= dt / pool.max_life[i];
+        pool.heat[i] = (pool.heat[i] - dt * emitter.heat_decay).max(0.0);
+
+        let age = 1.0 - pool.life[i].max(0.0);
+        pool.size[i] = pool.start_size[i] * emitter.size_over_life.sample(age);
     }
+
+    pool.retain(|pool, i| pool.life[i] > 0.0);
+}
+
+/// Color, opacity and blend mode of particle `i`
+fn particle_style(
+    pool: &ParticlePool,
+    i: usize,
+    library: &EffectLibrary,
+    appearance: &Appearance,
+) -> ((f64, f64, f64), f64, Blend) {
+    let emitter = library.emitter(pool.source[i]);
+    let age = 1.0 - pool.life[i].max(0.0);
+    (
+        appearance.color(emitter.gradient.sample(pool.heat[i])),
+        appearance.alpha(emitter.alpha_over_life.sample(age) * emitter.alpha_over_heat.sample(pool.heat[i])),
+        appearance.blend(emitter.blend),
+    )
 }
 
 // ============ LIVING PIXELS ============
@@ -148,6 +260,24 @@ struct LivingPixel {
     life: f64,
     kind: LivingKind,
     phase: f64,
+    // Position on the kind's color gradient, picked at spawn
+    tint: f64,
+}
+
+impl LivingPixel {
+    /// Screen area this pixel paints, including twinkle rays and trails
+    fn bounds(&self) -> Bounds {
+        match self.kind {
+            LivingKind::Star => Bounds::around(self.x, self.y, 4.5),
+            LivingKind::ShootingStar => {
+                let mut bounds = Bounds::around(self.x, self.y, 2.5 * GLOW_SCALE);
+                bounds.include(self.x - self.vx * 0.02, self.y - self.vy * 0.02, 2.0 * GLOW_SCALE);
+                bounds
+            }
+            // Halo at its largest, on a beat
+            LivingKind::Firefly => Bounds::around(self.x, self.y, FIREFLY_HALO * 1.5 * GLOW_SCALE),
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -157,73 +287,382 @@ enum LivingKind {
     Firefly,
 }
 
+/// Radius of a firefly's halo between beats
+const FIREFLY_HALO: f64 = 4.0;
+
+/// How stars twinkle and fireflies pulse this frame, for both renderers
+struct LivingLook {
+    depth: f64,
+    twinkle_depth: f64,
+    beat: f64,
+}
+
+impl LivingLook {
+    fn new(state: &EffectsState) -> Self {
+        // Twinkle and pulse depth; barely there with reduced motion
+        let depth = if state.reduce_motion() { 0.15 } else { 0.5 };
+        // With audio_reactive, treble deepens the twinkle (down to fully
+        // dark, so it is clamped there) and beats light fireflies up
+        Self {
+            depth,
+            twinkle_depth: depth * (1.0 + state.treble),
+            beat: state.beat_glow * depth * 2.0,
+        }
+    }
+
+    /// A star's brightness, 0..1
+    fn twinkle(&self, p: &LivingPixel, time: f64) -> f64 {
+        (1.0 - self.twinkle_depth + self.twinkle_depth * (p.phase + time * 3.0).sin()).max(0.0)
+    }
+
+    /// A firefly's brightness, then its halo's radius and alpha factor
+    fn firefly(&self, p: &LivingPixel, time: f64) -> (f64, f64, f64) {
+        let pulse = (1.0 - self.depth + self.depth * (p.phase + time * 4.0).sin()).max(self.beat);
+        (pulse, FIREFLY_HALO * (1.0 + 0.5 * self.beat), pulse * 0.5)
+    }
+}
+
+/// Living pixel colors; each can be replaced through Config::gradients
+/// under "stars", "shooting_stars" or "fireflies"
+struct LivingColors {
+    stars: Gradient,
+    // Sampled from the head (0) to the end of the trail (1)
+    shooting_stars: Gradient,
+    fireflies: Gradient,
+}
+
+impl LivingColors {
+    fn new(config: &Config) -> Self {
+        // With follow_accent, stars and fireflies default to accent palettes
+        let pick = |name: &str, accent_palette: Option<&str>, default: Vec<(f64, [f64; 3])>| {
+            if let Some(gradient) = config.gradients.get(name) {
+                return gradient.clone();
+            }
+            match accent_palette {
+                Some(palette) if config.follow_accent => Gradient::palette(palette).expect("built-in palette"),
+                _ => Gradient::new(default, Interpolation::Linear),
+            }
+        };
+        Self {
+            stars: pick("stars", Some("accent_star"), vec![(0.0, [1.0, 1.0, 0.95])]),
+            shooting_stars: pick("shooting_stars", None, vec![(0.0, [1.0, 1.0, 1.0]), (1.0, [1.0, 1.0, 0.9])]),
+            fireflies: pick("fireflies", Some("accent_glow"), vec![(0.0, [0.7, 1.0, 0.3]), (1.0, [0.9, 1.0, 0.5])]),
+        }
+    }
+
+    fn set_accent(&mut self, accent: [f64; 3]) {
+        self.stars.set_accent(accent);
+        self.shooting_stars.set_accent(accent);
+        self.fireflies.set_accent(accent);
+    }
+}
+
 // ============ STATE ============
 
+/// Most living pixels alive at once, budget permitting
+const LIVING_MAX_PIXELS: usize = 100;
+
 struct EffectsState {
-    fires: Vec<FireEffect>,
+    library: EffectLibrary,
+    // Library index of the effect started by touches
+    touch_effect: usize,
+    effects: Vec<EffectInstance>,
+    next_effect_id: u32,
+    // Pulses and edge glows requested by apps
+    oneshots: Vec<OneShot>,
+    // Particles of every effect, bounded by config.particle_budget
+    particles: ParticlePool,
+    // Rhai scripted effects
+    scripts: ScriptHost,
     living_pixels: Vec<LivingPixel>,
-    last_spawn: Instant,
+    living_colors: LivingColors,
+    living_quota: usize,
+    // Reused by tick() to split the budget without allocating
+    budget_caps: Vec<usize>,
+    budget_shares: Vec<usize>,
+    // Simulation time since living pixels were last spawned
+    spawn_timer: f64,
     config: Config,
     width: i32,
     height: i32,
     time: f64,
+    // Single RNG shared by every effect so a seed reproduces a whole session
+    rng: StdRng,
+    // Detail level, adjusted from measured frame times
+    quality: Quality,
+    // Desktop light/high-contrast/night-light preferences
+    appearance: Appearance,
+    // Dims regions that would flash too often; see config.flash_limit
+    flash: FlashLimiter,
+    // Treble of the music while config.audio_reactive is on, and a glow
+    // that jumps up on each beat and fades
+    treble: f64,
+    beat_glow: f64,
+    // Now-playing spectrum for config.visualizer, and the color picked
+    // from the cover art of the track playing
+    spectrum: Spectrum,
+    art_color: Option<[f64; 3]>,
+    // Simulation steps run so far; recordings note the step each touch
+    // arrived at so a replay can feed it in at the same point
+    steps: u64,
+    // Recorded touches still to be fed in, with --replay
+    replay: Option<Replay>,
+    // Kept to rebuild the state on reload
+    seed_override: Option<u64>,
+    accent: Option<[f64; 3]>,
 }
 
 impl EffectsState {
-    fn new() -> Self {
+    fn new(seed_override: Option<u64>) -> Self {
+        let config = Config::load();
+        let seed = seed_override
+            .or(config.seed)
+            .unwrap_or_else(rand::random);
+        println!("Using RNG seed: {}", seed);
+
+        // Frame times vary between runs, so a fixed seed also pins the
+        // quality level; otherwise replays would spawn differently
+        let quality_min = if seed_override.or(config.seed).is_some() {
+            config.quality_max
+        } else {
+            config.quality_min
+        };
+        let quality = Quality::new(quality_min, config.quality_max, config.frame_budget_ms);
+
+        let mut library = EffectLibrary::load();
+        if config.follow_accent && !config.gradients.contains_key(DEFAULT_EFFECT) {
+            library.set_gradient(DEFAULT_EFFECT, &Gradient::palette("accent_fire").expect("built-in palette"));
+        }
+        for (name, gradient) in &config.gradients {
+            let living = matches!(name.as_str(), "stars" | "shooting_stars" | "fireflies");
+            if !living && !library.set_gradient(name, gradient) {
+                eprintln!("Gradient given for unknown effect '{}'", name);
+            }
+        }
+        let touch_effect = match library.find(&config.touch_effect) {
+            Some(index) if library.get(index).trigger == Trigger::Touch => index,
+            _ => {
+                eprintln!("No touch effect named '{}', using '{}'", config.touch_effect, DEFAULT_EFFECT);
+                library.find(DEFAULT_EFFECT).expect("built-in effect missing")
+            }
+        };
+
+        let mut effects = Vec::new();
+        for name in &config.ambient_effects {
+            match library.find(name) {
+                Some(index) if library.get(index).trigger == Trigger::Ambient => {
+                    let id = effects.len() as u32;
+                    effects.push(EffectInstance::new(id, index, library.get(index), 0.0, 0.0));
+                }
+                _ => eprintln!("No ambient effect named '{}'", name),
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let scripts = ScriptHost::load(&config.script_effects, config.script_time_limit_ms, &mut rng);
+
         Self {
-            fires: Vec::new(),
-            living_pixels: Vec::new(),
-            last_spawn: Instant::now(),
-            config: Config::load(),
+            library,
+            touch_effect,
+            scripts,
+            next_effect_id: effects.len() as u32,
+            effects,
+            oneshots: Vec::new(),
+            particles: ParticlePool::new(config.particle_budget),
+            living_pixels: Vec::with_capacity(LIVING_MAX_PIXELS),
+            living_colors: LivingColors::new(&config),
+            living_quota: LIVING_MAX_PIXELS,
+            budget_caps: Vec::new(),
+            budget_shares: Vec::new(),
+            spawn_timer: 0.0,
+            config,
             width: 540,
             height: 1170,
             time: 0.0,
+            rng,
+            quality,
+            appearance: Appearance::default(),
+            flash: FlashLimiter::new(),
+            treble: 0.0,
+            beat_glow: 0.0,
+            spectrum: Spectrum::new(),
+            art_color: None,
+            steps: 0,
+            replay: None,
+            seed_override,
+            accent: None,
         }
     }
 
     fn add_touch(&mut self, x: f64, y: f64) {
         if self.config.fire_touch_enabled {
-            self.fires.push(FireEffect::new(x, y));
+            let def = self.library.get(self.touch_effect);
+            self.effects.push(EffectInstance::new(self.next_effect_id, self.touch_effect, def, x, y));
+            self.next_effect_id = self.next_effect_id.wrapping_add(1);
         }
+        self.scripts.on_touch("down", x, y);
     }
 
     fn update_touch(&mut self, x: f64, y: f64) {
-        for fire in &mut self.fires {
-            if fire.is_active {
-                fire.x = x;
-                fire.y = y;
+        for effect in &mut self.effects {
+            if effect.follows_touch() {
+                effect.x = x;
+                effect.y = y;
                 break;
             }
         }
+        self.scripts.on_touch("move", x, y);
     }
 
-    fn end_touch(&mut self, _x: f64, _y: f64) {
-        for fire in &mut self.fires {
-            if fire.is_active {
-                fire.is_active = false;
+    fn end_touch(&mut self, x: f64, y: f64) {
+        for effect in &mut self.effects {
+            if effect.follows_touch() {
+                effect.is_active = false;
                 break;
             }
         }
+        self.scripts.on_touch("up", x, y);
+    }
+
+    fn touch(&mut self, event: touch::TouchEvent) {
+        match event {
+            touch::TouchEvent::Start(x, y) => self.add_touch(x, y),
+            touch::TouchEvent::Move(x, y) => self.update_touch(x, y),
+            touch::TouchEvent::End(x, y) => self.end_touch(x, y),
+        }
+    }
+
+    /// Recolor accent-derived palettes for a new theme accent
+    fn set_accent(&mut self, accent: [f64; 3]) {
+        self.accent = Some(accent);
+        self.library.set_accent(accent);
+        self.living_colors.set_accent(accent);
+        self.tint_spectrum();
+    }
+
+    /// Cover art color, or the accent for tracks without art
+    fn tint_spectrum(&mut self) {
+        let [r, g, b] = self.art_color.or(self.accent).unwrap_or([1.0, 1.0, 1.0]);
+        self.spectrum.color = (r, g, b);
+    }
+
+    /// Apply new desktop preferences. Colors only follow them with
+    /// config.follow_appearance; reduced motion always does.
+    fn set_appearance(&mut self, appearance: Appearance) {
+        let was_reduced = self.reduce_motion();
+        self.appearance = if self.config.follow_appearance {
+            appearance
+        } else {
+            Appearance { reduce_motion: appearance.reduce_motion, ..Appearance::default() }
+        };
+        self.motion_changed(was_reduced);
+    }
+
+    /// Stop whatever is already moving once motion becomes reduced, rather
+    /// than letting it play out
+    fn motion_changed(&mut self, was_reduced: bool) {
+        if self.reduce_motion() && !was_reduced {
+            println!("Reducing motion");
+            self.particles.retain(|_, _| false);
+            self.living_pixels.retain(|p| !matches!(p.kind, LivingKind::ShootingStar));
+        }
+    }
+
+    /// Alpha multiplier from the flash limiter for anything drawn at (x, y)
+    fn flash_gain(&self, x: f64, y: f64) -> f64 {
+        if self.config.flash_limit { self.flash.gain(x, y) } else { 1.0 }
+    }
+
+    fn reduce_motion(&self) -> bool {
+        self.config.reduce_motion || self.appearance.reduce_motion
+    }
+
+    /// Whether anything on screen still needs simulating. Ambient effects
+    /// are paused while motion is reduced.
+    fn is_animating(&self) -> bool {
+        let reduce_motion = self.reduce_motion();
+        self.effects.iter().any(|e| !(e.ambient && reduce_motion))
+            || !self.oneshots.is_empty()
+            || self.config.living_pixels_enabled
+            || self.scripts.is_animating()
+            || self.replay.as_ref().is_some_and(|r| !r.is_finished())
+            || (self.config.visualizer != Visualizer::Off && self.spectrum.is_animating())
+    }
+
+    /// Split the particle budget fairly between the active effects
+    fn distribute_budget(&mut self) {
+        self.budget_caps.clear();
+        let library = &self.library;
+        self.budget_caps.extend(self.effects.iter().map(|e| library.get(e.effect).max_particles));
+        if self.config.living_pixels_enabled {
+            self.budget_caps.push(LIVING_MAX_PIXELS);
+        }
+        self.budget_shares.resize(self.budget_caps.len(), 0);
+        let budget = (self.config.particle_budget as f64 * self.quality.level()) as usize;
+        fair_shares(budget, &self.budget_caps, &mut self.budget_shares);
+
+        for (effect, &share) in self.effects.iter_mut().zip(&self.budget_shares) {
+            effect.quota = share;
+        }
+        self.living_quota = self.budget_shares.get(self.effects.len()).copied().unwrap_or(0);
     }
 
     fn tick(&mut self, dt: f64) {
+        let due = self.replay.as_mut().map(|r| r.take_due(self.steps)).unwrap_or_default();
+        for event in due {
+            self.touch(event);
+        }
+        self.steps += 1;
         self.time += dt;
+        self.distribute_budget();
+
+        // Update effects
+        let rate = self.quality.level();
+        let screen = (self.width as f64, self.height as f64);
+        let reduce_motion = self.reduce_motion();
+        for effect in &mut self.effects {
+            effect.still = reduce_motion;
+            if effect.release_at.is_some_and(|t| effect.age >= t) {
+                effect.is_active = false;
+            }
+            if reduce_motion {
+                effect.update_still(dt);
+                continue;
+            }
+            let def = self.library.get(effect.effect);
+            effect.spawn_particles(def, dt, rate, screen, &mut self.rng, &mut self.particles);
+            effect.update(def, dt);
+        }
+        update_particles(&mut self.particles, &self.library, dt, self.time, &mut self.rng);
+        let particles = &self.particles;
+        self.effects.retain(|e| !e.is_done(particles));
+
+        for shot in &mut self.oneshots {
+            shot.update(dt);
+        }
+        self.oneshots.retain(|s| !s.is_done());
 
-        // Update fires
-        for fire in &mut self.fires {
-            fire.spawn_particles(dt);
-            fire.update(dt, self.time);
+        if self.config.visualizer != Visualizer::Off {
+            self.spectrum.update(dt, reduce_motion);
         }
-        self.fires.retain(|f| !f.is_done());
+
+        self.scripts.tick(dt, &Frame {
+            time: self.time,
+            width: self.width as f64,
+            height: self.height as f64,
+            quality: self.quality.level(),
+            reduce_motion,
+        });
 
         // Living pixels
         if self.config.living_pixels_enabled {
+            self.beat_glow = (self.beat_glow - dt * 4.0).max(0.0);
             self.update_living_pixels(dt);
 
-            if self.last_spawn.elapsed().as_millis() > 100 {
+            self.spawn_timer += dt;
+            if self.spawn_timer > 0.1 {
                 self.spawn_living_pixels();
-                self.last_spawn = Instant::now();
+                self.spawn_timer = 0.0;
             }
 
             self.living_pixels.retain(|p| p.life > 0.0);
@@ -233,6 +672,7 @@ impl EffectsState {
     fn update_living_pixels(&mut self, dt: f64) {
         let w = self.width as f64;
         let h = self.height as f64;
+        let reduce_motion = self.reduce_motion();
 
         for pixel in &mut self.living_pixels {
             pixel.phase += dt * 5.0;
@@ -246,9 +686,13 @@ impl EffectsState {
                     pixel.y += pixel.vy * dt;
                     pixel.life -= dt * 0.4;
                 }
+                // Reduced motion: fireflies glow in place
+                LivingKind::Firefly if reduce_motion => {
+                    pixel.life -= dt * 0.15;
+                }
                 LivingKind::Firefly => {
-                    pixel.vx += (rand::random::<f64>() - 0.5) * 100.0 * dt;
-                    pixel.vy += (rand::random::<f64>() - 0.5) * 100.0 * dt;
+                    pixel.vx += (self.rng.gen::<f64>() - 0.5) * 100.0 * dt;
+                    pixel.vy += (self.rng.gen::<f64>() - 0.5) * 100.0 * dt;
                     pixel.vx *= 0.95;
                     pixel.vy *= 0.95;
                     pixel.x += pixel.vx * dt;
@@ -265,14 +709,16 @@ impl EffectsState {
     }
 
     fn spawn_living_pixels(&mut self) {
-        if self.living_pixels.len() > 100 { return; }
+        if self.living_pixels.len() >= self.living_quota { return; }
 
-        let mut rng = rand::thread_rng();
+        let shooting_stars = self.config.lp_shooting_stars && !self.reduce_motion();
+        let rng = &mut self.rng;
+        let rate = self.quality.level();
         let w = self.width as f64;
         let h = self.height as f64;
 
         // Stars
-        if self.config.lp_stars && rng.gen::<f64>() < 0.3 {
+        if self.config.lp_stars && rng.gen::<f64>() < 0.3 * rate {
             self.living_pixels.push(LivingPixel {
                 x: rng.gen::<f64>() * w,
                 y: rng.gen::<f64>() * h * 0.6,
@@ -280,11 +726,12 @@ impl EffectsState {
                 life: 1.0,
                 kind: LivingKind::Star,
                 phase: rng.gen::<f64>() * PI * 2.0,
+                tint: rng.gen::<f64>(),
             });
         }
 
         // Shooting stars
-        if self.config.lp_shooting_stars && rng.gen::<f64>() < 0.02 {
+        if shooting_stars && rng.gen::<f64>() < 0.02 * rate {
             self.living_pixels.push(LivingPixel {
                 x: rng.gen::<f64>() * w * 0.5,
                 y: rng.gen::<f64>() * h * 0.3,
@@ -293,11 +740,12 @@ impl EffectsState {
                 life: 1.0,
                 kind: LivingKind::ShootingStar,
                 phase: 0.0,
+                tint: 0.0,
             });
         }
 
         // Fireflies
-        if self.config.lp_fireflies && rng.gen::<f64>() < 0.08 {
+        if self.config.lp_fireflies && rng.gen::<f64>() < 0.08 * rate {
             self.living_pixels.push(LivingPixel {
                 x: rng.gen::<f64>() * w,
                 y: rng.gen::<f64>() * h,
@@ -306,77 +754,766 @@ impl EffectsState {
                 life: 1.0,
                 kind: LivingKind::Firefly,
                 phase: rng.gen::<f64>() * PI * 2.0,
+                tint: rng.gen::<f64>(),
             });
         }
+
+        // Several kinds can spawn at once; never exceed the share
+        self.living_pixels.truncate(self.living_quota);
+    }
+}
+
+// ============ CONTROL ============
+
+/// Runtime changes requested over D-Bus. They last until the next reload
+/// or restart; the config file is left alone.
+impl EffectsState {
+    fn set_enabled(&mut self, feature: &str, enabled: bool) -> Result<(), String> {
+        match feature {
+            "touch" => self.config.fire_touch_enabled = enabled,
+            "living-pixels" => {
+                self.config.living_pixels_enabled = enabled;
+                if !enabled {
+                    self.living_pixels.clear();
+                }
+            }
+            "stars" => self.config.lp_stars = enabled,
+            "shooting-stars" => self.config.lp_shooting_stars = enabled,
+            "fireflies" => self.config.lp_fireflies = enabled,
+            "flash-limit" => self.config.flash_limit = enabled,
+            "notifications" => self.config.notification_glow = enabled,
+            "audio" => self.config.audio_reactive = enabled,
+            "calls" => {
+                self.config.call_ring = enabled;
+                if !enabled {
+                    self.set_ringing(false);
+                }
+            }
+            "reduce-motion" => {
+                let was_reduced = self.reduce_motion();
+                self.config.reduce_motion = enabled;
+                self.motion_changed(was_reduced);
+            }
+            name => return self.set_effect_enabled(name, enabled),
+        }
+        Ok(())
+    }
+
+    /// Enabling a touch effect makes it the touch effect; ambient effects
+    /// start or fade out
+    fn set_effect_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        let index = self.library.find(name).ok_or_else(|| format!("no feature or effect named '{}'", name))?;
+        match self.library.get(index).trigger {
+            Trigger::Touch if enabled => {
+                self.touch_effect = index;
+                self.config.touch_effect = name.to_string();
+                self.config.fire_touch_enabled = true;
+            }
+            Trigger::Touch => {
+                if self.touch_effect == index {
+                    self.config.fire_touch_enabled = false;
+                }
+            }
+            Trigger::Ambient if enabled => {
+                if !self.config.ambient_effects.iter().any(|e| e == name) {
+                    self.config.ambient_effects.push(name.to_string());
+                    let id = self.next_effect_id;
+                    self.next_effect_id = self.next_effect_id.wrapping_add(1);
+                    self.effects.push(EffectInstance::new(id, index, self.library.get(index), 0.0, 0.0));
+                }
+            }
+            Trigger::Ambient => {
+                self.config.ambient_effects.retain(|e| e != name);
+                for effect in self.effects.iter_mut().filter(|e| e.ambient && e.effect == index) {
+                    effect.is_active = false;
+                }
+            }
+            Trigger::Event => return Err(format!("'{}' only plays on request", name)),
+        }
+        Ok(())
+    }
+
+    fn set_touch_effect(&mut self, name: &str) -> Result<(), String> {
+        match self.library.find(name) {
+            Some(index) if self.library.get(index).trigger == Trigger::Touch => {
+                self.touch_effect = index;
+                self.config.touch_effect = name.to_string();
+                Ok(())
+            }
+            Some(_) => Err(format!("'{}' is not a touch effect", name)),
+            None => Err(format!("no effect named '{}'", name)),
+        }
+    }
+
+    /// Recolor an effect or a kind of living pixel. `spec` is a palette
+    /// name or a gradient in any of the JSON forms the config accepts.
+    fn set_gradient(&mut self, target: &str, spec: &str) -> Result<(), String> {
+        let mut gradient = if spec.trim_start().starts_with(['[', '{', '"']) {
+            serde_json::from_str::<Gradient>(spec).map_err(|e| e.to_string())?
+        } else {
+            Gradient::palette(spec)?
+        };
+        if let Some(accent) = self.accent {
+            gradient.set_accent(accent);
+        }
+        match target {
+            "stars" => self.living_colors.stars = gradient,
+            "shooting_stars" => self.living_colors.shooting_stars = gradient,
+            "fireflies" => self.living_colors.fireflies = gradient,
+            name => {
+                if !self.library.set_gradient(name, &gradient) {
+                    return Err(format!("no effect named '{}'", name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Play a touch or event effect at (x, y) as if held for `params.duration`
+    fn trigger(&mut self, name: &str, x: f64, y: f64, params: &TriggerParams) -> Result<(), String> {
+        let index = self.library.find(name).ok_or_else(|| format!("no effect named '{}'", name))?;
+        if self.library.get(index).trigger == Trigger::Ambient {
+            return Err(format!("'{}' is an ambient effect", name));
+        }
+        if !x.is_finite() || !y.is_finite() {
+            return Err("position must be finite".to_string());
+        }
+        self.start_released(index, x, y, params.duration);
+        Ok(())
+    }
+
+    /// Play a named one-shot effect. Pulses and edge glows are drawn
+    /// directly; any other name must be an event effect from the library.
+    fn play(&mut self, name: &str, params: &PlayParams) -> Result<(), String> {
+        let (width, height) = (self.width as f64, self.height as f64);
+        let [r, g, b] = params.color.or(self.accent).unwrap_or([1.0, 1.0, 1.0]);
+        match name {
+            "pulse" => {
+                let at = OneShotKind::Pulse {
+                    x: params.x.unwrap_or(width / 2.0),
+                    y: params.y.unwrap_or(height / 2.0),
+                };
+                self.oneshots.push(OneShot::new(at, (r, g, b), params.duration.unwrap_or(0.8)));
+            }
+            "edge-glow" => {
+                self.oneshots.push(OneShot::new(OneShotKind::EdgeGlow, (r, g, b), params.duration.unwrap_or(1.5)));
+            }
+            _ => {
+                let index = self.library.find(name).ok_or_else(|| format!("no effect named '{}'", name))?;
+                let def = self.library.get(index);
+                if def.trigger != Trigger::Event {
+                    return Err(format!("'{}' is not an event effect", name));
+                }
+                if params.color.is_some() {
+                    return Err("color only applies to pulse and edge-glow".to_string());
+                }
+                let x = params.x.unwrap_or(def.origin[0] * width);
+                let y = params.y.unwrap_or(def.origin[1] * height);
+                let duration = params.duration.unwrap_or(def.duration);
+                self.start_released(index, x, y, duration);
+            }
+        }
+        Ok(())
+    }
+
+    /// Glow along the edges for a notification, in its urgency's color
+    /// unless notification_apps picks one for the app or mutes it
+    fn notify(&mut self, notification: &Notification) {
+        if !self.config.notification_glow {
+            return;
+        }
+        let apps = &self.config.notification_apps;
+        let app = notification
+            .desktop_entry
+            .as_ref()
+            .and_then(|entry| apps.get(entry))
+            .or_else(|| apps.get(&notification.app_name));
+        let urgency = notification.urgency;
+        let [r, g, b] = match app {
+            Some(AppGlow::Enabled(false)) => return,
+            Some(AppGlow::Color(color)) => theme::parse_hex_color(color).unwrap_or_else(|| {
+                eprintln!("Bad notification color '{}' for {}", color, notification.app_name);
+                urgency.color(self.accent)
+            }),
+            Some(AppGlow::Enabled(true)) | None => urgency.color(self.accent),
+        };
+        // A burst of notifications shares one full-screen glow, in the
+        // latest one's color, rather than stacking up glows
+        let glow = self.oneshots.iter_mut().find(|s| s.kind == OneShotKind::EdgeGlow && !s.is_done());
+        match glow {
+            Some(glow) => glow.renew((r, g, b), urgency.duration()),
+            None => self.oneshots.push(OneShot::new(OneShotKind::EdgeGlow, (r, g, b), urgency.duration())),
+        }
+    }
+
+    /// Levels of the music playing now; default levels once it stops
+    fn hear(&mut self, levels: AudioLevels) {
+        self.spectrum.hear(&levels);
+        // The visualizer may be listening while living pixels are not
+        if !self.config.audio_reactive {
+            self.treble = 0.0;
+            return;
+        }
+        self.treble = levels.treble;
+        if levels.beat {
+            self.beat_glow = 1.0;
+        }
+    }
+
+    /// Fade the spectrum in or out
+    fn set_now_playing(&mut self, playing: bool) {
+        self.spectrum.playing = playing;
+    }
+
+    /// Tint the spectrum with the cover art's color, or the accent without
+    fn set_art_color(&mut self, art_color: Option<[f64; 3]>) {
+        self.art_color = art_color;
+        self.tint_spectrum();
+    }
+
+    /// Ring from the edges until the call is answered or missed
+    fn set_ringing(&mut self, ringing: bool) {
+        for shot in self.oneshots.iter_mut().filter(|s| s.kind == OneShotKind::EdgeRing) {
+            shot.stop();
+        }
+        if ringing && self.config.call_ring {
+            let [r, g, b] = calls::RING_COLOR;
+            self.oneshots.push(OneShot::new(OneShotKind::EdgeRing, (r, g, b), f64::INFINITY));
+        }
+    }
+
+    /// Start an effect that lets go by itself after `duration` seconds
+    fn start_released(&mut self, index: usize, x: f64, y: f64, duration: f64) {
+        let mut effect = EffectInstance::new(self.next_effect_id, index, self.library.get(index), x, y);
+        effect.release_at = Some(duration);
+        self.effects.push(effect);
+        self.next_effect_id = self.next_effect_id.wrapping_add(1);
+    }
+
+    /// Start over from the config file and effect files, keeping what
+    /// comes from the desktop and the screen
+    fn reload(&mut self) {
+        let mut state = EffectsState::new(self.seed_override);
+        state.width = self.width;
+        state.height = self.height;
+        state.set_appearance(self.appearance);
+        if let Some(accent) = self.accent.filter(|_| state.config.follow_accent) {
+            state.set_accent(accent);
+        }
+        state.set_now_playing(self.spectrum.playing);
+        state.set_art_color(self.art_color);
+        state.steps = self.steps;
+        state.replay = self.replay.take();
+        *self = state;
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            touch_enabled: self.config.fire_touch_enabled,
+            touch_effect: self.library.get(self.touch_effect).name.clone(),
+            ambient_effects: self.config.ambient_effects.clone(),
+            living_pixels: self.config.living_pixels_enabled,
+            reduce_motion: self.reduce_motion(),
+            available_effects: self.library.names().map(str::to_string).collect(),
+            scripts: self.scripts.scripts().iter().map(|s| s.name.clone()).collect(),
+            active_effects: (self.effects.len() + self.oneshots.len()) as u32,
+            particles: self.particles.len() as u32,
+            quality: self.quality.level(),
+        }
+    }
+}
+
+// ============ TIMING ============
+
+/// Simulation step, independent of the panel's refresh rate
+const SIM_STEP: f64 = 1.0 / 60.0;
+/// Most steps simulated for one frame; longer gaps (suspend, stalls) are dropped
+const MAX_CATCH_UP_STEPS: u32 = 4;
+
+/// Converts frame clock timestamps into a whole number of fixed simulation steps
+struct FixedStep {
+    last_frame_us: Option<i64>,
+    accumulator: f64,
+}
+
+impl FixedStep {
+    fn new() -> Self {
+        Self {
+            last_frame_us: None,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Number of SIM_STEPs to run for a frame presented at `frame_time_us`
+    fn advance(&mut self, frame_time_us: i64) -> u32 {
+        match self.last_frame_us {
+            Some(last) => self.accumulator += (frame_time_us - last).max(0) as f64 / 1_000_000.0,
+            // First frame after waking up: step once so the touch shows immediately
+            None => self.accumulator += SIM_STEP,
+        }
+        self.last_frame_us = Some(frame_time_us);
+
+        let mut steps = 0;
+        while self.accumulator >= SIM_STEP && steps < MAX_CATCH_UP_STEPS {
+            self.accumulator -= SIM_STEP;
+            steps += 1;
+        }
+        if self.accumulator >= SIM_STEP {
+            // Too far behind to catch up; skip ahead instead of bursting
+            self.accumulator = 0.0;
+        }
+        steps
+    }
+}
+
+// ============ OVERLAY ============
+
+/// State shared by the main loop callbacks
+struct Overlay {
+    window: ApplicationWindow,
+    state: RefCell<EffectsState>,
+    // Widget currently rendering the effects (GLArea or EffectsCanvas)
+    canvas: RefCell<Option<gtk4::Widget>>,
+    ticking: Cell<bool>,
+    // File monitors and similar objects that must live as long as the overlay
+    watchers: RefCell<Vec<glib::Object>>,
+    // Set while config.notification_glow / config.call_ring /
+    // config.audio_reactive are on
+    notifications: RefCell<Option<NotificationWatcher>>,
+    calls: RefCell<Option<CallWatcher>>,
+    mpris: RefCell<Option<MprisWatcher>>,
+    // Cover art of the current track, whose color tints the spectrum
+    art_url: RefCell<Option<String>>,
+    // Set while a media player is playing, with audio_reactive on
+    audio: RefCell<Option<AudioAnalyzer>>,
+}
+
+impl Overlay {
+    fn new(window: ApplicationWindow, state: EffectsState) -> Rc<Self> {
+        Rc::new(Self {
+            window,
+            state: RefCell::new(state),
+            canvas: RefCell::new(None),
+            ticking: Cell::new(false),
+            watchers: RefCell::new(Vec::new()),
+            notifications: RefCell::new(None),
+            calls: RefCell::new(None),
+            mpris: RefCell::new(None),
+            art_url: RefCell::new(None),
+            audio: RefCell::new(None),
+        })
+    }
+
+    /// Start or stop watching notifications, calls and media players to
+    /// match the config
+    fn follow_events(self: &Rc<Self>) {
+        let (notification_glow, call_ring, media) = {
+            let config = &self.state.borrow().config;
+            // audio_reactive only changes living pixels
+            let audio_reactive = config.audio_reactive && config.living_pixels_enabled;
+            (config.notification_glow, config.call_ring, audio_reactive || config.visualizer != Visualizer::Off)
+        };
+
+        let mut notifications = self.notifications.borrow_mut();
+        if !notification_glow {
+            notifications.take();
+        } else if notifications.is_none() {
+            let overlay = Rc::downgrade(self);
+            let result = NotificationWatcher::new(move |notification| {
+                let Some(overlay) = overlay.upgrade() else { return };
+                overlay.state.borrow_mut().notify(&notification);
+                overlay.wake();
+            });
+            match result {
+                Ok(watcher) => *notifications = Some(watcher),
+                Err(e) => eprintln!("Cannot watch notifications: {}", e),
+            }
+        }
+
+        let mut calls = self.calls.borrow_mut();
+        if !call_ring {
+            calls.take();
+        } else if calls.is_none() {
+            let overlay = Rc::downgrade(self);
+            let result = CallWatcher::new(move |ringing| {
+                let Some(overlay) = overlay.upgrade() else { return };
+                overlay.state.borrow_mut().set_ringing(ringing);
+                overlay.wake();
+            });
+            match result {
+                Ok(watcher) => *calls = Some(watcher),
+                Err(e) => eprintln!("Cannot watch calls: {}", e),
+            }
+        }
+
+        let mut mpris = self.mpris.borrow_mut();
+        if !media {
+            if mpris.take().is_some() {
+                self.state.borrow_mut().set_now_playing(false);
+                self.show_art(None);
+                self.listen(false);
+            }
+        } else if mpris.is_none() {
+            let overlay = Rc::downgrade(self);
+            let result = MprisWatcher::new(move |now: &NowPlaying| {
+                let Some(overlay) = overlay.upgrade() else { return };
+                overlay.state.borrow_mut().set_now_playing(now.playing);
+                overlay.show_art(now.art_url.clone());
+                overlay.listen(now.playing);
+                overlay.wake();
+            });
+            match result {
+                Ok(watcher) => *mpris = Some(watcher),
+                Err(e) => eprintln!("Cannot watch media players: {}", e),
+            }
+        }
+    }
+
+    /// Tint the spectrum with the cover art at `url` once it is decoded,
+    /// which happens off the main loop
+    fn show_art(self: &Rc<Self>, url: Option<String>) {
+        if *self.art_url.borrow() == url {
+            return;
+        }
+        self.art_url.replace(url.clone());
+        let Some(url) = url else {
+            self.state.borrow_mut().set_art_color(None);
+            return;
+        };
+        let overlay = Rc::downgrade(self);
+        glib::spawn_future_local(async move {
+            let art_color = visualizer::art_color(&url).await;
+            let Some(overlay) = overlay.upgrade() else { return };
+            // The track may have changed while this one decoded
+            if overlay.art_url.borrow().as_deref() != Some(url.as_str()) {
+                return;
+            }
+            overlay.state.borrow_mut().set_art_color(art_color);
+            overlay.redraw();
+        });
+    }
+
+    /// Analyze the default sink's audio while a media player is playing
+    fn listen(self: &Rc<Self>, playing: bool) {
+        let mut audio = self.audio.borrow_mut();
+        if !playing {
+            if audio.take().is_some() {
+                self.state.borrow_mut().hear(AudioLevels::default());
+            }
+            return;
+        }
+        if audio.is_some() {
+            return;
+        }
+        let overlay = Rc::downgrade(self);
+        let ended = overlay.clone();
+        let result = AudioAnalyzer::new(
+            move |levels| {
+                let Some(overlay) = overlay.upgrade() else { return };
+                overlay.state.borrow_mut().hear(levels);
+            },
+            move || {
+                let Some(overlay) = ended.upgrade() else { return };
+                // Capture again on the player's next change
+                eprintln!("Audio capture stopped");
+                overlay.audio.borrow_mut().take();
+                overlay.state.borrow_mut().hear(AudioLevels::default());
+            },
+        );
+        match result {
+            Ok(analyzer) => *audio = Some(analyzer),
+            Err(e) => eprintln!("Cannot capture audio: {}", e),
+        }
+    }
+
+    fn keep_alive(&self, watcher: impl IsA<glib::Object>) {
+        self.watchers.borrow_mut().push(watcher.upcast());
+    }
+
+    /// Repaint once without starting the simulation, e.g. after a color change
+    fn redraw(&self) {
+        if let Some(canvas) = self.canvas.borrow().as_ref() {
+            canvas.queue_draw();
+        }
+    }
+
+    /// Report the D-Bus properties after a change from outside the
+    /// interface: a reload, or the desktop turning reduced motion on or off
+    fn properties_changed(&self) {
+        if let Some(connection) = self.window.application().and_then(|app| app.dbus_connection()) {
+            dbus::properties_changed(&connection, &self.state.borrow().status());
+        }
+    }
+
+    /// Show effects on `canvas`, replacing any previous renderer
+    fn use_canvas(self: &Rc<Self>, canvas: gtk4::Widget) {
+        self.window.set_child(Some(&canvas));
+        *self.canvas.borrow_mut() = Some(canvas);
+        // Tick callbacks belong to the old widget; start over on the new one
+        self.ticking.set(false);
+        self.wake();
+    }
+
+    /// Drive the simulation from the frame clock until nothing is animating.
+    /// The tick callback removes itself when idle so the process sleeps until
+    /// the next touch event calls this again.
+    fn wake(self: &Rc<Self>) {
+        if !self.state.borrow().is_animating() || self.ticking.get() {
+            return;
+        }
+        let Some(canvas) = self.canvas.borrow().clone() else { return };
+        self.ticking.set(true);
+
+        let overlay = self.clone();
+        let fixed_step = RefCell::new(FixedStep::new());
+        let measured = RefCell::new((Vec::new(), Vec::new()));
+        canvas.add_tick_callback(move |canvas, frame_clock| {
+            let steps = fixed_step.borrow_mut().advance(frame_clock.frame_time());
+            let mut state = overlay.state.borrow_mut();
+            let start = Instant::now();
+            for _ in 0..steps {
+                state.tick(SIM_STEP);
+            }
+
+            // Estimate the frame about to be drawn so the limiter can dim it
+            if state.config.flash_limit {
+                let (over, add) = &mut *measured.borrow_mut();
+                over.clear();
+                add.clear();
+                collect_sprites(&state, over, add);
+                let (width, height) = (state.width as f64, state.height as f64);
+                let time = frame_clock.frame_time() as f64 / 1_000_000.0;
+                state.flash.update(&[over, add], width, height, time);
+            }
+            state.quality.add_work(start.elapsed());
+
+            // The previous frame's draw time has been added by now
+            if state.quality.end_frame() {
+                println!(
+                    "Quality level {:.2} (frame work {:.1} ms)",
+                    state.quality.level(),
+                    state.quality.frame_ms()
+                );
+            }
+
+            // Always draw, so the last frame clears whatever was left on screen
+            canvas.queue_draw();
+            if state.is_animating() {
+                ControlFlow::Continue
+            } else {
+                overlay.ticking.set(false);
+                ControlFlow::Break
+            }
+        });
+    }
+}
+
+/// Every change redraws, and wakes the simulation if there is now
+/// something to animate
+impl dbus::Controller for Overlay {
+    fn set_enabled(self: &Rc<Self>, feature: &str, enabled: bool) -> Result<(), String> {
+        self.state.borrow_mut().set_enabled(feature, enabled)?;
+        println!("{} {} over D-Bus", if enabled { "Enabled" } else { "Disabled" }, feature);
+        self.follow_events();
+        self.redraw();
+        self.wake();
+        Ok(())
+    }
+
+    fn set_effect(self: &Rc<Self>, name: &str) -> Result<(), String> {
+        self.state.borrow_mut().set_touch_effect(name)?;
+        println!("Touch effect set to '{}' over D-Bus", name);
+        Ok(())
+    }
+
+    fn set_gradient(self: &Rc<Self>, target: &str, gradient: &str) -> Result<(), String> {
+        self.state.borrow_mut().set_gradient(target, gradient)?;
+        println!("Gradient for '{}' set over D-Bus", target);
+        self.redraw();
+        Ok(())
+    }
+
+    fn reload_config(self: &Rc<Self>) {
+        println!("Reloading config");
+        self.state.borrow_mut().reload();
+        self.follow_events();
+        // The reloaded state has no effects running; bring back the ring
+        // of a call that is still ringing
+        if self.calls.borrow().as_ref().is_some_and(CallWatcher::is_ringing) {
+            self.state.borrow_mut().set_ringing(true);
+        }
+        self.redraw();
+        self.wake();
+        self.properties_changed();
+    }
+
+    fn trigger_effect(self: &Rc<Self>, name: &str, x: f64, y: f64, params: &TriggerParams) -> Result<(), String> {
+        self.state.borrow_mut().trigger(name, x, y, params)?;
+        self.wake();
+        Ok(())
+    }
+
+    fn play(self: &Rc<Self>, name: &str, params: &PlayParams) -> Result<(), String> {
+        self.state.borrow_mut().play(name, params)?;
+        println!("Playing '{}' over D-Bus", name);
+        self.wake();
+        Ok(())
+    }
+
+    fn client_policy(self: &Rc<Self>) -> ClientPolicy {
+        let state = self.state.borrow();
+        ClientPolicy {
+            allowed: state.config.trigger_clients.clone(),
+            per_minute: state.config.trigger_rate_per_minute,
+        }
+    }
+
+    fn status(self: &Rc<Self>) -> Status {
+        self.state.borrow().status()
     }
 }
 
 // ============ DRAWING ============
 
-fn draw_effects(cr: &gtk4::cairo::Context, state: &EffectsState) {
-    cr.set_operator(gtk4::cairo::Operator::Source);
-    cr.set_source_rgba(0.0, 0.0, 0.0, 0.0);
-    cr.paint().ok();
-    cr.set_operator(gtk4::cairo::Operator::Over);
+/// Paint each effect into its own layer sized to its bounds, so only the
+/// areas that changed since the last frame get redrawn and damaged
+fn draw_effects(layers: &mut Layers, state: &EffectsState, sprites: &mut SpriteCache) {
+    let glow = state.quality.glow();
 
     // Draw living pixels
-    draw_living_pixels(cr, &state.living_pixels, state.time);
+    for p in &state.living_pixels {
+        layers.paint(p.bounds(), |cr| {
+            draw_living_pixels(cr, std::slice::from_ref(p), state, glow, sprites);
+        });
+    }
 
-    // Draw fires
-    for fire in &state.fires {
-        draw_fire(cr, fire);
+    // Draw emitter effects
+    for effect in &state.effects {
+        let def = state.library.get(effect.effect);
+        layers.paint(effect.bounds(def, &state.particles), |cr| {
+            draw_effect(cr, effect, state, glow, sprites);
+        });
+    }
+
+    // Draw scripted effects
+    for script in state.scripts.scripts() {
+        layers.paint(script.bounds(), |cr| draw_script(cr, &script.commands(), state, sprites));
+    }
+
+    // Draw pulses and edge glows
+    let (width, height) = (state.width as f64, state.height as f64);
+    for shot in &state.oneshots {
+        layers.paint(shot.bounds(width, height), |cr| draw_one_shot(cr, shot, state, sprites));
+    }
+
+    // Draw the now-playing spectrum
+    let style = state.config.visualizer;
+    if style != Visualizer::Off && state.spectrum.is_visible() {
+        layers.paint(state.spectrum.bounds(style, width, height), |cr| draw_spectrum(cr, style, state, sprites));
     }
 }
 
-fn draw_fire(cr: &gtk4::cairo::Context, fire: &FireEffect) {
-    for p in &fire.particles {
-        let alpha = (p.life * 1.5).min(1.0);
-        let (r, g, b, _) = heat_to_color(p.heat, alpha);
-        cr.set_source_rgba(r, g, b, alpha * 0.9);
-        cr.arc(p.x, p.y, p.size, 0.0, 2.0 * PI);
-        cr.fill().ok();
+fn draw_spectrum(cr: &gtk4::cairo::Context, style: Visualizer, state: &EffectsState, sprites: &mut SpriteCache) {
+    let mut dots = Vec::new();
+    state.spectrum.sprites(style, state.width as f64, state.height as f64, &mut dots);
+    let color = state.appearance.color(state.spectrum.color);
+    for dot in &dots {
+        let (x, y) = (dot.x as f64, dot.y as f64);
+        let alpha = state.appearance.alpha(dot.a as f64) * state.flash_gain(x, y);
+        sprites.draw(cr, x, y, dot.radius as f64, color, alpha);
     }
+}
 
-    if fire.is_active && fire.intensity > 0.3 {
-        cr.set_source_rgba(1.0, 0.5, 0.1, 0.2 * fire.intensity);
-        cr.arc(fire.x, fire.y, 30.0 * fire.intensity, 0.0, 2.0 * PI);
-        cr.fill().ok();
+fn draw_one_shot(cr: &gtk4::cairo::Context, shot: &OneShot, state: &EffectsState, sprites: &mut SpriteCache) {
+    let mut dots = Vec::new();
+    shot.sprites(state.width as f64, state.height as f64, state.reduce_motion(), &mut dots);
+    let color = state.appearance.color(shot.color);
+    for dot in &dots {
+        let (x, y) = (dot.x as f64, dot.y as f64);
+        let alpha = state.appearance.alpha(dot.a as f64) * state.flash_gain(x, y);
+        sprites.draw(cr, x, y, dot.radius as f64, color, alpha);
     }
 }
 
-fn heat_to_color(heat: f64, alpha: f64) -> (f64, f64, f64, f64) {
-    if heat > 0.9 {
-        (1.0, 1.0, 0.9, alpha)
-    } else if heat > 0.7 {
-        let t = (heat - 0.7) / 0.2;
-        (1.0, 0.9 + t * 0.1, 0.3 + t * 0.6, alpha)
-    } else if heat > 0.5 {
-        let t = (heat - 0.5) / 0.2;
-        (1.0, 0.5 + t * 0.4, 0.1 + t * 0.2, alpha)
-    } else if heat > 0.3 {
-        let t = (heat - 0.3) / 0.2;
-        (0.9 + t * 0.1, 0.2 + t * 0.3, 0.05 + t * 0.05, alpha)
-    } else if heat > 0.1 {
-        let t = (heat - 0.1) / 0.2;
-        (0.5 + t * 0.4, 0.05 + t * 0.15, 0.02 + t * 0.03, alpha * (0.5 + t * 0.5))
-    } else {
-        (0.2, 0.2, 0.2, alpha * 0.3)
+/// `glow` adds the soft light under the finger
+fn draw_effect(cr: &gtk4::cairo::Context, effect: &EffectInstance, state: &EffectsState, glow: bool, sprites: &mut SpriteCache) {
+    let pool = &state.particles;
+    let appearance = &state.appearance;
+    for i in pool.owned_by(effect.id) {
+        let (color, alpha, blend) = particle_style(pool, i, &state.library, appearance);
+        cr.set_operator(match blend {
+            Blend::Over => Operator::Over,
+            Blend::Add => Operator::Add,
+        });
+        let alpha = alpha * state.flash_gain(pool.x[i], pool.y[i]);
+        sprites.draw(cr, pool.x[i], pool.y[i], pool.size[i], color, alpha);
+    }
+    cr.set_operator(Operator::Over);
+
+    let Some((radius, color, alpha)) = effect.glow(state.library.get(effect.effect)) else { return };
+    let alpha = alpha * state.flash_gain(effect.x, effect.y);
+    // The reduced motion dot is the only feedback left, so it ignores quality
+    if glow || effect.still {
+        sprites.draw(cr, effect.x, effect.y, radius, appearance.color(color), appearance.alpha(alpha));
+    }
+    // High contrast: ring the touch point so it stands out on any background
+    if let Some((r, g, b)) = appearance.outline() {
+        cr.set_source_rgba(r, g, b, alpha.sqrt());
+        cr.set_line_width(2.0);
+        cr.arc(effect.x, effect.y, radius * 0.6, 0.0, 2.0 * PI);
+        cr.stroke().ok();
+    }
+}
+
+/// Replay what a script recorded in its draw()
+fn draw_script(cr: &gtk4::cairo::Context, commands: &[DrawCommand], state: &EffectsState, sprites: &mut SpriteCache) {
+    for command in commands {
+        let (r, g, b) = state.appearance.color(command.color);
+        let (x, y) = command.shape.center();
+        let alpha = state.appearance.alpha(command.alpha) * state.flash_gain(x, y);
+        match command.shape {
+            Shape::Glow { x, y, radius } => sprites.draw(cr, x, y, radius, (r, g, b), alpha),
+            Shape::Circle { x, y, radius } => {
+                cr.set_source_rgba(r, g, b, alpha);
+                cr.arc(x, y, radius, 0.0, 2.0 * PI);
+                cr.fill().ok();
+            }
+            Shape::Line { x0, y0, x1, y1, width } => {
+                cr.set_source_rgba(r, g, b, alpha);
+                cr.set_line_width(width);
+                cr.move_to(x0, y0);
+                cr.line_to(x1, y1);
+                cr.stroke().ok();
+            }
+        }
     }
 }
 
-fn draw_living_pixels(cr: &gtk4::cairo::Context, pixels: &[LivingPixel], time: f64) {
+/// `glow` adds star sparkles and firefly halos
+fn draw_living_pixels(
+    cr: &gtk4::cairo::Context,
+    pixels: &[LivingPixel],
+    state: &EffectsState,
+    glow: bool,
+    sprites: &mut SpriteCache,
+) {
+    let colors = &state.living_colors;
+    let appearance = &state.appearance;
+    let time = state.time;
+    let look = LivingLook::new(state);
     for p in pixels {
-        let alpha = p.life.min(1.0);
+        let alpha = appearance.alpha(p.life.min(1.0)) * state.flash_gain(p.x, p.y);
 
         match p.kind {
             LivingKind::Star => {
-                let twinkle = 0.5 + 0.5 * (p.phase + time * 3.0).sin();
-                cr.set_source_rgba(1.0, 1.0, 0.95, alpha * twinkle * 0.9);
+                let twinkle = look.twinkle(p, time);
+                let (r, g, b) = appearance.color(colors.stars.sample(p.tint));
+                cr.set_source_rgba(r, g, b, alpha * twinkle * 0.9);
                 cr.arc(p.x, p.y, 1.5, 0.0, 2.0 * PI);
                 cr.fill().ok();
 
-                if twinkle > 0.7 {
-                    cr.set_source_rgba(1.0, 1.0, 1.0, alpha * (twinkle - 0.7) * 2.0);
+                if glow && twinkle > 0.7 {
+                    cr.set_source_rgba(r, g, b, alpha * (twinkle - 0.7) * 2.0);
                     cr.set_line_width(0.5);
                     let len = 4.0 * twinkle;
                     cr.move_to(p.x - len, p.y);
@@ -392,35 +1529,250 @@ fn draw_living_pixels(cr: &gtk4::cairo::Context, pixels: &[LivingPixel], time: f
                     let tx = p.x - p.vx * 0.02 * t;
                     let ty = p.y - p.vy * 0.02 * t;
                     let ta = alpha * (1.0 - t) * 0.8;
-                    cr.set_source_rgba(1.0, 1.0, 0.9, ta);
-                    cr.arc(tx, ty, 2.0 * (1.0 - t * 0.5), 0.0, 2.0 * PI);
-                    cr.fill().ok();
+                    sprites.draw(cr, tx, ty, 2.0 * (1.0 - t * 0.5), appearance.color(colors.shooting_stars.sample(t)), ta);
                 }
-                cr.set_source_rgba(1.0, 1.0, 1.0, alpha);
-                cr.arc(p.x, p.y, 2.5, 0.0, 2.0 * PI);
-                cr.fill().ok();
+                sprites.draw(cr, p.x, p.y, 2.5, appearance.color(colors.shooting_stars.sample(0.0)), alpha);
             }
             LivingKind::Firefly => {
-                let glow = 0.5 + 0.5 * (p.phase + time * 4.0).sin();
-                cr.set_source_rgba(0.7, 1.0, 0.3, alpha * glow * 0.4);
-                cr.arc(p.x, p.y, 8.0, 0.0, 2.0 * PI);
-                cr.fill().ok();
-                cr.set_source_rgba(0.9, 1.0, 0.5, alpha * glow);
-                cr.arc(p.x, p.y, 2.0, 0.0, 2.0 * PI);
-                cr.fill().ok();
+                let (pulse, halo, halo_alpha) = look.firefly(p, time);
+                let color = appearance.color(colors.fireflies.sample(p.tint));
+                if glow {
+                    sprites.draw(cr, p.x, p.y, halo, color, alpha * halo_alpha);
+                }
+                sprites.draw(cr, p.x, p.y, 2.0, color, alpha * pulse);
             }
         }
     }
 }
 
+// ============ GL SPRITES ============
+
+/// Flatten the scene into glow sprites for the GL renderer, mirroring
+/// what draw_effects paints with cairo. Additively blended particles go
+/// into `add`, everything else into `out`.
+fn collect_sprites(state: &EffectsState, out: &mut Vec<Sprite>, add: &mut Vec<Sprite>) {
+    let time = state.time;
+    let look = LivingLook::new(state);
+    let glow = state.quality.glow();
+    let colors = &state.living_colors;
+    let appearance = &state.appearance;
+
+    for p in &state.living_pixels {
+        let alpha = appearance.alpha(p.life.min(1.0));
+
+        match p.kind {
+            LivingKind::Star => {
+                let twinkle = look.twinkle(p, time);
+                let (r, g, b) = appearance.color(colors.stars.sample(p.tint));
+                out.push(Sprite::new(p.x, p.y, 1.5, r, g, b, alpha * twinkle * 0.9));
+                if glow && twinkle > 0.7 {
+                    out.push(Sprite::new(p.x, p.y, 2.0 * twinkle, r, g, b, alpha * (twinkle - 0.7) * 2.0));
+                }
+            }
+            LivingKind::ShootingStar => {
+                for i in 0..8 {
+                    let t = i as f64 / 8.0;
+                    let tx = p.x - p.vx * 0.02 * t;
+                    let ty = p.y - p.vy * 0.02 * t;
+                    let (r, g, b) = appearance.color(colors.shooting_stars.sample(t));
+                    out.push(Sprite::new(tx, ty, 2.0 * (1.0 - t * 0.5), r, g, b, alpha * (1.0 - t) * 0.8));
+                }
+                let (r, g, b) = appearance.color(colors.shooting_stars.sample(0.0));
+                out.push(Sprite::new(p.x, p.y, 2.5, r, g, b, alpha));
+            }
+            LivingKind::Firefly => {
+                let (pulse, halo, halo_alpha) = look.firefly(p, time);
+                let (r, g, b) = appearance.color(colors.fireflies.sample(p.tint));
+                if glow {
+                    out.push(Sprite::new(p.x, p.y, halo, r, g, b, alpha * halo_alpha));
+                }
+                out.push(Sprite::new(p.x, p.y, 2.0, r, g, b, alpha * pulse));
+            }
+        }
+    }
+
+    let pool = &state.particles;
+    for i in 0..pool.len() {
+        let ((r, g, b), alpha, blend) = particle_style(pool, i, &state.library, appearance);
+        let sprite = Sprite::new(pool.x[i], pool.y[i], pool.size[i], r, g, b, alpha);
+        match blend {
+            Blend::Over => out.push(sprite),
+            Blend::Add => add.push(sprite),
+        }
+    }
+
+    for script in state.scripts.scripts() {
+        for command in script.commands().iter() {
+            script_sprites(command, appearance, out);
+        }
+    }
+
+    let (width, height) = (state.width as f64, state.height as f64);
+    for shot in &state.oneshots {
+        let start = out.len();
+        shot.sprites(width, height, state.reduce_motion(), out);
+        let (r, g, b) = appearance.color(shot.color);
+        for sprite in &mut out[start..] {
+            (sprite.r, sprite.g, sprite.b) = (r as f32, g as f32, b as f32);
+            sprite.a = appearance.alpha(sprite.a as f64) as f32;
+        }
+    }
+
+    let style = state.config.visualizer;
+    if style != Visualizer::Off && state.spectrum.is_visible() {
+        let start = out.len();
+        state.spectrum.sprites(style, width, height, out);
+        let (r, g, b) = appearance.color(state.spectrum.color);
+        for sprite in &mut out[start..] {
+            (sprite.r, sprite.g, sprite.b) = (r as f32, g as f32, b as f32);
+            sprite.a = appearance.alpha(sprite.a as f64) as f32;
+        }
+    }
+
+    for effect in &state.effects {
+        let Some((radius, color, alpha)) = effect.glow(state.library.get(effect.effect)) else { continue };
+        if glow || effect.still {
+            let (r, g, b) = appearance.color(color);
+            out.push(Sprite::new(effect.x, effect.y, radius, r, g, b, appearance.alpha(alpha)));
+        }
+        // The high contrast ring, as a circle of dots
+        if let Some((r, g, b)) = appearance.outline() {
+            let ring = radius * 0.6;
+            let steps = (ring * 2.0 * PI).ceil().clamp(8.0, 256.0) as usize;
+            for i in 0..steps {
+                let angle = i as f64 / steps as f64 * 2.0 * PI;
+                let (x, y) = (effect.x + ring * angle.cos(), effect.y + ring * angle.sin());
+                out.push(Sprite::new(x, y, 1.0, r, g, b, alpha.sqrt()));
+            }
+        }
+    }
+}
+
+/// GL approximation of a script draw call: lines become a row of dots
+fn script_sprites(command: &DrawCommand, appearance: &Appearance, out: &mut Vec<Sprite>) {
+    let (r, g, b) = appearance.color(command.color);
+    let alpha = appearance.alpha(command.alpha);
+    match command.shape {
+        Shape::Glow { x, y, radius } => out.push(Sprite::new(x, y, radius, r, g, b, alpha)),
+        Shape::Circle { x, y, radius } => out.push(Sprite::new(x, y, radius, r, g, b, alpha)),
+        Shape::Line { x0, y0, x1, y1, width } => {
+            let radius = (width / 2.0).max(0.5);
+            let steps = ((x1 - x0).hypot(y1 - y0) / radius).ceil().clamp(1.0, 256.0) as usize;
+            for i in 0..=steps {
+                let t = i as f64 / steps as f64;
+                out.push(Sprite::new(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t, radius, r, g, b, alpha));
+            }
+        }
+    }
+}
+
+// ============ CANVAS ============
+
+/// Cairo renderer, always available
+fn build_cairo_canvas(overlay: &Rc<Overlay>) -> gtk4::Widget {
+    let canvas = EffectsCanvas::new();
+    canvas.set_css_classes(&["transparent"]);
+
+    let overlay = overlay.clone();
+    let sprites = RefCell::new(SpriteCache::new());
+    canvas.set_draw_func(move |layers, w, h| {
+        let mut state = overlay.state.borrow_mut();
+        state.width = w;
+        state.height = h;
+        let start = Instant::now();
+        draw_effects(layers, &state, &mut sprites.borrow_mut());
+        state.quality.add_work(start.elapsed());
+    });
+
+    canvas.upcast()
+}
+
+/// GLES renderer; swaps itself for the cairo renderer if GL can't be set up
+fn build_gl_area(overlay: &Rc<Overlay>) -> gtk4::Widget {
+    let area = GLArea::new();
+    area.set_use_es(true);
+    area.set_required_version(3, 0);
+    area.set_has_alpha(true);
+    area.set_css_classes(&["transparent"]);
+
+    let renderer: Rc<RefCell<Option<GlRenderer>>> = Rc::new(RefCell::new(None));
+
+    let renderer_realize = renderer.clone();
+    let overlay_realize = overlay.clone();
+    area.connect_realize(move |area| {
+        area.make_current();
+        let result = match area.error() {
+            Some(e) => Err(e.to_string()),
+            None => GlRenderer::new(),
+        };
+
+        match result {
+            Ok(gl_renderer) => {
+                println!("Using GL renderer");
+                *renderer_realize.borrow_mut() = Some(gl_renderer);
+            }
+            Err(e) => {
+                eprintln!("GL renderer unavailable ({}), falling back to cairo", e);
+                // Can't replace the widget while it is being realized
+                let overlay = overlay_realize.clone();
+                glib::idle_add_local_once(move || {
+                    overlay.use_canvas(build_cairo_canvas(&overlay));
+                });
+            }
+        }
+    });
+
+    let renderer_unrealize = renderer.clone();
+    area.connect_unrealize(move |area| {
+        // GL objects must be deleted with their context current
+        area.make_current();
+        renderer_unrealize.borrow_mut().take();
+    });
+
+    let overlay = overlay.clone();
+    let sprites = RefCell::new((Vec::new(), Vec::new()));
+    area.connect_render(move |area, _| {
+        let mut state = overlay.state.borrow_mut();
+        state.width = area.width();
+        state.height = area.height();
+
+        if let Some(gl_renderer) = renderer.borrow_mut().as_mut() {
+            let start = Instant::now();
+            let (over, add) = &mut *sprites.borrow_mut();
+            over.clear();
+            add.clear();
+            collect_sprites(&state, over, add);
+            for sprite in over.iter_mut().chain(add.iter_mut()) {
+                sprite.a *= state.flash_gain(sprite.x as f64, sprite.y as f64) as f32;
+            }
+            gl_renderer.render(&[(Blend::Over, over), (Blend::Add, add)], state.width, state.height);
+            state.quality.add_work(start.elapsed());
+        }
+        glib::Propagation::Stop
+    });
+
+    area.upcast()
+}
+
 // ============ MAIN ============
 
 fn main() {
+    let args = Args::parse();
+    let seed = args.seed;
+    let record_path = args.record.clone();
+    let replay_path = args.replay.clone();
+    let renderer_override = args.renderer;
+
     let app = Application::builder()
         .application_id("org.flick.Effects")
         .build();
 
-    app.connect_activate(|app| {
+    app.connect_activate(move |app| {
+        // Launching again only activates the running instance, which
+        // already has its overlay
+        if !app.windows().is_empty() {
+            return;
+        }
         let window = ApplicationWindow::builder()
             .application(app)
             .title("Flick Effects")
@@ -452,61 +1804,110 @@ fn main() {
             }
         });
 
-        let drawing_area = DrawingArea::new();
-
         let da_css = gtk4::CssProvider::new();
         da_css.load_from_data(".transparent { background-color: transparent; }");
-        drawing_area.set_css_classes(&["transparent"]);
         gtk4::style_context_add_provider_for_display(
             &gdk4::Display::default().unwrap(),
             &da_css,
             gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
         );
 
-        window.set_child(Some(&drawing_area));
+        let state = EffectsState::new(seed);
+        let renderer = renderer_override.unwrap_or(state.config.renderer);
+        let in_main_loop = state.config.touch_in_main_loop;
+        let follow_accent = state.config.follow_accent;
+        let overlay = Overlay::new(window.clone(), state);
 
-        let state = Rc::new(RefCell::new(EffectsState::new()));
-        let state_draw = state.clone();
-        let state_tick = state.clone();
-        let state_events = state.clone();
+        // Match the shell theme's accent color, now and whenever it changes
+        if follow_accent {
+            if let Some(accent) = theme::read_accent() {
+                overlay.state.borrow_mut().set_accent(accent);
+            }
+            let overlay_theme = overlay.clone();
+            let monitor = theme::watch_accent(move |accent| {
+                overlay_theme.state.borrow_mut().set_accent(accent);
+                overlay_theme.redraw();
+            });
+            if let Some(monitor) = monitor {
+                overlay.keep_alive(monitor);
+            }
+        }
 
-        let (tx, rx) = mpsc::channel::<touch::TouchEvent>();
+        // Light mode, high contrast, night light and reduced motion from
+        // GSettings; set_appearance ignores the colors without follow_appearance
+        let overlay_appearance = overlay.clone();
+        let appearance = desktop::watch_appearance(move |appearance| {
+            let was_reduced = overlay_appearance.state.borrow().reduce_motion();
+            overlay_appearance.state.borrow_mut().set_appearance(appearance);
+            overlay_appearance.redraw();
+            // Ambient effects resume when motion is allowed again
+            overlay_appearance.wake();
+            if overlay_appearance.state.borrow().reduce_motion() != was_reduced {
+                overlay_appearance.properties_changed();
+            }
+        });
+        overlay.state.borrow_mut().set_appearance(appearance);
 
-        let _touch_monitor = TouchMonitor::new(move |event| {
-            let _ = tx.send(event);
+        let mut recorder = record_path.as_deref().and_then(|path| {
+            Recorder::create(path)
+                .map_err(|e| eprintln!("Cannot record to {:?}: {}", path, e))
+                .ok()
         });
 
-        glib::timeout_add_local(std::time::Duration::from_millis(8), move || {
-            while let Ok(event) = rx.try_recv() {
-                let mut state = state_events.borrow_mut();
-                match event {
-                    touch::TouchEvent::Start(x, y) => state.add_touch(x, y),
-                    touch::TouchEvent::Move(x, y) => state.update_touch(x, y),
-                    touch::TouchEvent::End(x, y) => state.end_touch(x, y),
+        // Runs on the main loop as soon as the input thread queues an event
+        let overlay_events = overlay.clone();
+        let on_event = move |event| {
+            {
+                let mut state = overlay_events.state.borrow_mut();
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.record(event, state.steps);
                 }
+                state.touch(event);
             }
-            ControlFlow::Continue
-        });
-
-        drawing_area.set_draw_func(move |_, cr, w, h| {
-            let mut state = state_draw.borrow_mut();
-            state.width = w;
-            state.height = h;
-            draw_effects(cr, &state);
-        });
+            overlay_events.wake();
+        };
+
+        // A replay is fed in by tick() instead of the touchscreen
+        let _touch_monitor = match replay_path.as_deref() {
+            Some(path) => {
+                match Replay::load(path, SIM_STEP) {
+                    Ok(replay) => overlay.state.borrow_mut().replay = Some(replay),
+                    Err(e) => eprintln!("Touch replay error: {}", e),
+                }
+                None
+            }
+            None if in_main_loop => Some(TouchMonitor::in_main_loop(on_event)),
+            None => Some(TouchMonitor::new(on_event)),
+        };
+
+        // Attaching the canvas also starts any ambient effects animating
+        let canvas = match renderer {
+            Renderer::Gl => build_gl_area(&overlay),
+            Renderer::Cairo => build_cairo_canvas(&overlay),
+        };
+        overlay.use_canvas(canvas);
+
+        match app.dbus_connection() {
+            Some(connection) => {
+                if let Err(e) = dbus::export(&connection, overlay.clone()) {
+                    eprintln!("Cannot export D-Bus control interface: {}", e);
+                }
+            }
+            None => eprintln!("No session bus, D-Bus control disabled"),
+        }
 
-        let last_tick = Rc::new(RefCell::new(Instant::now()));
-        glib::timeout_add_local(std::time::Duration::from_millis(25), move || {
-            let dt = last_tick.borrow().elapsed().as_secs_f64();
-            *last_tick.borrow_mut() = Instant::now();
+        overlay.follow_events();
 
-            state_tick.borrow_mut().tick(dt);
-            drawing_area.queue_draw();
+        // Older scripts signal a reload with SIGHUP
+        let overlay_hup = overlay.clone();
+        glib::unix_signal_add_local(libc::SIGHUP, move || {
+            dbus::Controller::reload_config(&overlay_hup);
             ControlFlow::Continue
         });
 
         window.present();
     });
 
-    app.run();
+    // Our own flags are parsed above; don't let GApplication reject them
+    app.run_with_args(&[args.program]);
 }
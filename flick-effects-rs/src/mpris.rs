@@ -0,0 +1,205 @@
+use gio::prelude::*;
+use glib::Variant;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// What the media players are doing, as far as the effects care
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NowPlaying {
+    /// Whether any player is playing
+    pub playing: bool,
+    /// Cover art of the track playing (mpris:artUrl), if it has any
+    pub art_url: Option<String>,
+}
+
+/// Follows the MPRIS media players on the session bus and reports whenever
+/// one starts or stops playing, or the playing track's art changes.
+/// Pointing DBUS_SESSION_BUS_ADDRESS at a private bus runs it against a
+/// mock (`cargo run --example mock-mpris`). Watching stops when this is
+/// dropped.
+pub struct MprisWatcher {
+    connection: gio::DBusConnection,
+    subscriptions: Vec<gio::SignalSubscriptionId>,
+}
+
+#[derive(Default)]
+struct Player {
+    playing: bool,
+    art_url: Option<String>,
+}
+
+/// Players by unique bus name, and what was last reported
+struct Players {
+    players: RefCell<HashMap<String, Player>>,
+    reported: RefCell<NowPlaying>,
+    on_change: Box<dyn Fn(&NowPlaying)>,
+}
+
+impl Players {
+    /// Apply changed Player properties (PlaybackStatus, Metadata) from `owner`
+    fn update(&self, owner: &str, properties: &HashMap<String, Variant>) {
+        {
+            let mut players = self.players.borrow_mut();
+            let player = players.entry(owner.to_string()).or_default();
+            if let Some(status) = properties.get("PlaybackStatus").and_then(|s| s.get::<String>()) {
+                player.playing = status == "Playing";
+            }
+            if let Some(metadata) = properties.get("Metadata").and_then(|m| m.get::<HashMap<String, Variant>>()) {
+                player.art_url = metadata.get("mpris:artUrl").and_then(|a| a.get::<String>()).filter(|a| !a.is_empty());
+            }
+        }
+        self.report();
+    }
+
+    fn remove(&self, owner: &str) {
+        if self.players.borrow_mut().remove(owner).is_some() {
+            self.report();
+        }
+    }
+
+    fn report(&self) {
+        let now = {
+            let players = self.players.borrow();
+            let playing = players.values().find(|p| p.playing);
+            NowPlaying {
+                playing: playing.is_some(),
+                art_url: playing.and_then(|p| p.art_url.clone()),
+            }
+        };
+        if now != *self.reported.borrow() {
+            println!("Media {}", if now.playing { "playing" } else { "stopped" });
+            self.reported.replace(now.clone());
+            (self.on_change)(&now);
+        }
+    }
+
+    /// Ask `name` for all its Player properties and record them under `owner`
+    fn query(self: &Rc<Self>, connection: &gio::DBusConnection, name: &str, owner: String) {
+        let players = self.clone();
+        connection.call(
+            Some(name),
+            MPRIS_PATH,
+            "org.freedesktop.DBus.Properties",
+            "GetAll",
+            Some(&(PLAYER_INTERFACE,).to_variant()),
+            None,
+            gio::DBusCallFlags::NO_AUTO_START,
+            1000,
+            gio::Cancellable::NONE,
+            move |result| {
+                let properties = result.ok().and_then(|reply| reply.get::<(HashMap<String, Variant>,)>());
+                if let Some((properties,)) = properties {
+                    players.update(&owner, &properties);
+                }
+            },
+        );
+    }
+}
+
+impl MprisWatcher {
+    pub fn new<F>(on_change: F) -> Result<Self, glib::Error>
+    where
+        F: Fn(&NowPlaying) + 'static,
+    {
+        let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)?;
+        let players = Rc::new(Players {
+            players: RefCell::new(HashMap::new()),
+            reported: RefCell::new(NowPlaying::default()),
+            on_change: Box::new(on_change),
+        });
+
+        // Signals come from the player's unique name, so that is the key
+        let mut subscriptions = Vec::new();
+        let players_changed = players.clone();
+        subscriptions.push(connection.signal_subscribe(
+            None,
+            Some("org.freedesktop.DBus.Properties"),
+            Some("PropertiesChanged"),
+            Some(MPRIS_PATH),
+            Some(PLAYER_INTERFACE),
+            gio::DBusSignalFlags::NONE,
+            move |_, sender, _, _, _, params| {
+                if let Some((_, changed, _)) = params.get::<(String, HashMap<String, Variant>, Vec<String>)>() {
+                    players_changed.update(sender, &changed);
+                }
+            },
+        ));
+
+        // Players coming and going
+        let players_owner = players.clone();
+        subscriptions.push(connection.signal_subscribe(
+            Some("org.freedesktop.DBus"),
+            Some("org.freedesktop.DBus"),
+            Some("NameOwnerChanged"),
+            Some("/org/freedesktop/DBus"),
+            None,
+            gio::DBusSignalFlags::NONE,
+            move |connection, _, _, _, _, params| {
+                let Some((name, old_owner, new_owner)) = params.get::<(String, String, String)>() else { return };
+                if !name.starts_with(MPRIS_PREFIX) {
+                    return;
+                }
+                if !old_owner.is_empty() {
+                    players_owner.remove(&old_owner);
+                }
+                if !new_owner.is_empty() {
+                    players_owner.query(connection, &name, new_owner);
+                }
+            },
+        ));
+
+        // Players that were already there when we started
+        let players_listed = players.clone();
+        let list_connection = connection.clone();
+        connection.call(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "ListNames",
+            None,
+            None,
+            gio::DBusCallFlags::NONE,
+            1000,
+            gio::Cancellable::NONE,
+            move |result| {
+                let Some((names,)) = result.ok().and_then(|reply| reply.get::<(Vec<String>,)>()) else { return };
+                for name in names.into_iter().filter(|n| n.starts_with(MPRIS_PREFIX)) {
+                    let players = players_listed.clone();
+                    let connection = list_connection.clone();
+                    list_connection.call(
+                        Some("org.freedesktop.DBus"),
+                        "/org/freedesktop/DBus",
+                        "org.freedesktop.DBus",
+                        "GetNameOwner",
+                        Some(&(name.as_str(),).to_variant()),
+                        None,
+                        gio::DBusCallFlags::NONE,
+                        1000,
+                        gio::Cancellable::NONE,
+                        move |result| {
+                            if let Some((owner,)) = result.ok().and_then(|reply| reply.get::<(String,)>()) {
+                                players.query(&connection, &name, owner);
+                            }
+                        },
+                    );
+                }
+            },
+        );
+
+        println!("Watching media players");
+        Ok(Self { connection, subscriptions })
+    }
+}
+
+impl Drop for MprisWatcher {
+    fn drop(&mut self) {
+        for id in self.subscriptions.drain(..) {
+            self.connection.signal_unsubscribe(id);
+        }
+    }
+}
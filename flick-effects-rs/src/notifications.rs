@@ -0,0 +1,131 @@
+use gio::prelude::*;
+use glib::Variant;
+use std::collections::HashMap;
+
+use crate::desktop;
+use crate::touch::attach_to_main_loop;
+
+const NOTIFICATIONS_SCHEMA: &str = "org.gnome.desktop.notifications";
+
+/// Match rule for the calls we want to see
+const NOTIFY_RULE: &str = "type='method_call',interface='org.freedesktop.Notifications',member='Notify'";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    /// Edge glow color; normal notifications use the theme accent
+    pub fn color(self, accent: Option<[f64; 3]>) -> [f64; 3] {
+        match self {
+            Urgency::Low => [0.6, 0.7, 0.85],
+            Urgency::Normal => accent.unwrap_or([1.0, 1.0, 1.0]),
+            Urgency::Critical => [1.0, 0.2, 0.15],
+        }
+    }
+
+    /// Seconds the edge glow lasts
+    pub fn duration(self) -> f64 {
+        match self {
+            Urgency::Low => 1.0,
+            Urgency::Normal => 1.5,
+            Urgency::Critical => 3.0,
+        }
+    }
+}
+
+/// The parts of a Notify call that decide the glow
+#[derive(Debug)]
+pub struct Notification {
+    pub app_name: String,
+    // desktop-entry hint, when the sender gives one
+    pub desktop_entry: Option<String>,
+    pub urgency: Urgency,
+}
+
+impl Notification {
+    /// Notify(susssasa{sv}i) arguments
+    fn parse(body: &Variant) -> Option<Self> {
+        if body.n_children() < 7 {
+            return None;
+        }
+        let app_name = body.child_value(0).get::<String>()?;
+        let hints = body.child_value(6).get::<HashMap<String, Variant>>()?;
+        let urgency = match hints.get("urgency").and_then(|u| u.get::<u8>()) {
+            Some(0) => Urgency::Low,
+            Some(2) => Urgency::Critical,
+            _ => Urgency::Normal,
+        };
+        let desktop_entry = hints.get("desktop-entry").and_then(|d| d.get::<String>());
+        Some(Self { app_name, desktop_entry, urgency })
+    }
+}
+
+/// Watches org.freedesktop.Notifications traffic on the session bus
+/// through a monitor connection, which can see calls meant for the
+/// notification daemon but can't send anything itself. Watching stops
+/// when this is dropped.
+pub struct NotificationWatcher {
+    connection: gio::DBusConnection,
+}
+
+impl NotificationWatcher {
+    /// Call `on_notify` on the main loop for each notification that is
+    /// shown as a banner; none are while do-not-disturb is on
+    pub fn new<F>(on_notify: F) -> Result<Self, glib::Error>
+    where
+        F: Fn(Notification) + 'static,
+    {
+        let address = gio::dbus_address_get_for_bus_sync(gio::BusType::Session, gio::Cancellable::NONE)?;
+        let connection = gio::DBusConnection::for_address_sync(
+            &address,
+            gio::DBusConnectionFlags::AUTHENTICATION_CLIENT | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+            None,
+            gio::Cancellable::NONE,
+        )?;
+
+        // show-banners is false while do-not-disturb is on
+        let settings = desktop::settings(NOTIFICATIONS_SCHEMA).filter(|s| desktop::has_key(s, "show-banners"));
+        let sender = attach_to_main_loop(move |notification: Notification| {
+            if settings.as_ref().is_some_and(|s| !s.boolean("show-banners")) {
+                return;
+            }
+            on_notify(notification);
+        });
+
+        // Runs on GDBus's worker thread. Monitored calls are dropped here so
+        // GDBus doesn't try to answer them; everything else is ours.
+        connection.add_filter(move |_, message, incoming| {
+            if incoming && message.message_type() == gio::DBusMessageType::MethodCall {
+                if let Some(notification) = message.body().as_ref().and_then(Notification::parse) {
+                    sender.send(notification);
+                }
+                return None;
+            }
+            Some(message.clone())
+        });
+
+        connection.call_sync(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus.Monitoring",
+            "BecomeMonitor",
+            Some(&(vec![NOTIFY_RULE], 0u32).to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            1000,
+            gio::Cancellable::NONE,
+        )?;
+        println!("Watching notifications");
+        Ok(Self { connection })
+    }
+}
+
+impl Drop for NotificationWatcher {
+    fn drop(&mut self) {
+        self.connection.close(gio::Cancellable::NONE, |_| {});
+    }
+}
@@ -0,0 +1,134 @@
+use std::f64::consts::PI;
+
+use crate::bounds::Bounds;
+use crate::gl_renderer::Sprite;
+use crate::sprites::GLOW_SCALE;
+
+/// Radius of the glows lining the screen edges
+const EDGE_RADIUS: f64 = 28.0;
+const EDGE_ALPHA: f64 = 0.5;
+/// Edge glow brightness swings per second
+const EDGE_PULSE_HZ: f64 = 1.0;
+/// Size a pulse ring grows to, and of the dots drawing it
+const PULSE_RADIUS: f64 = 120.0;
+const PULSE_DOT: f64 = 4.0;
+/// Edge ring: seconds between waves, and how far in from the edges they
+/// travel before fading
+const RING_PERIOD: f64 = 1.0;
+const RING_INSET: f64 = 90.0;
+const RING_DOT: f64 = 10.0;
+/// Seconds to fade in at the start and out at the end
+const FADE_IN: f64 = 0.15;
+const FADE_OUT: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OneShotKind {
+    /// Ring spreading out from a point
+    Pulse { x: f64, y: f64 },
+    /// Light along all four screen edges
+    EdgeGlow,
+    /// Edge glow with waves moving in from the edges, until stopped
+    EdgeRing,
+}
+
+/// Drawn (rather than particle) effect that plays once on request
+pub struct OneShot {
+    pub kind: OneShotKind,
+    pub color: (f64, f64, f64),
+    age: f64,
+    duration: f64,
+}
+
+impl OneShot {
+    pub fn new(kind: OneShotKind, color: (f64, f64, f64), duration: f64) -> Self {
+        Self { kind, color, age: 0.0, duration }
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        self.age += dt;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.age >= self.duration
+    }
+
+    /// Play on in a new color for at least another `duration` seconds,
+    /// without fading out and in again
+    pub fn renew(&mut self, color: (f64, f64, f64), duration: f64) {
+        self.color = color;
+        self.duration = self.duration.max(self.age + duration);
+    }
+
+    /// Fade out now, for effects started with an unbounded duration
+    pub fn stop(&mut self) {
+        self.duration = self.duration.min(self.age + FADE_OUT);
+    }
+
+    fn envelope(&self) -> f64 {
+        let fade_in = (self.age / FADE_IN).min(1.0);
+        let fade_out = ((self.duration - self.age) / FADE_OUT).clamp(0.0, 1.0);
+        fade_in * fade_out
+    }
+
+    /// The effect as glow sprites, shared by both renderers. `still`
+    /// (reduced motion) keeps the ring from growing and the edges from pulsing.
+    pub fn sprites(&self, width: f64, height: f64, still: bool, out: &mut Vec<Sprite>) {
+        let (r, g, b) = self.color;
+        match self.kind {
+            OneShotKind::Pulse { x, y } => {
+                let progress = (self.age / self.duration).min(1.0);
+                let (radius, alpha) = if still {
+                    (PULSE_RADIUS * 0.3, 0.6 * self.envelope())
+                } else {
+                    // Eases out, fading as it spreads
+                    (PULSE_RADIUS * (1.0 - (1.0 - progress).powi(2)), 0.8 * (1.0 - progress))
+                };
+                let steps = (radius * 2.0 * PI / PULSE_DOT).ceil().clamp(8.0, 256.0) as usize;
+                for i in 0..steps {
+                    let angle = i as f64 / steps as f64 * 2.0 * PI;
+                    out.push(Sprite::new(x + radius * angle.cos(), y + radius * angle.sin(), PULSE_DOT, r, g, b, alpha));
+                }
+            }
+            OneShotKind::EdgeGlow | OneShotKind::EdgeRing => {
+                let pulse = if still { 1.0 } else { 0.7 + 0.3 * (self.age * EDGE_PULSE_HZ * 2.0 * PI).cos() };
+                let alpha = EDGE_ALPHA * self.envelope() * pulse;
+                rectangle(width, height, 0.0, EDGE_RADIUS, (r, g, b, alpha), out);
+                if self.kind == OneShotKind::EdgeRing && !still {
+                    // Two waves in flight, each fading as it moves in
+                    for wave in 0..2 {
+                        let phase = (self.age / RING_PERIOD + wave as f64 * 0.5).fract();
+                        let alpha = EDGE_ALPHA * self.envelope() * (1.0 - phase);
+                        rectangle(width, height, phase * RING_INSET, RING_DOT, (r, g, b, alpha), out);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn bounds(&self, width: f64, height: f64) -> Bounds {
+        match self.kind {
+            OneShotKind::Pulse { x, y } => Bounds::around(x, y, PULSE_RADIUS + PULSE_DOT * GLOW_SCALE),
+            OneShotKind::EdgeGlow | OneShotKind::EdgeRing => Bounds { x0: 0.0, y0: 0.0, x1: width, y1: height },
+        }
+    }
+}
+
+/// Glows of `radius` around a rectangle `inset` pixels in from the screen edges
+fn rectangle(width: f64, height: f64, inset: f64, radius: f64, (r, g, b, a): (f64, f64, f64, f64), out: &mut Vec<Sprite>) {
+    let (x0, y0, x1, y1) = (inset, inset, width - inset, height - inset);
+    if x1 <= x0 || y1 <= y0 {
+        return;
+    }
+    let cols = ((x1 - x0) / radius).ceil().max(1.0) as usize;
+    for i in 0..=cols {
+        let x = x0 + i as f64 * (x1 - x0) / cols as f64;
+        out.push(Sprite::new(x, y0, radius, r, g, b, a));
+        out.push(Sprite::new(x, y1, radius, r, g, b, a));
+    }
+    let rows = ((y1 - y0) / radius).ceil().max(1.0) as usize;
+    for i in 1..rows {
+        let y = y0 + i as f64 * (y1 - y0) / rows as f64;
+        out.push(Sprite::new(x0, y, radius, r, g, b, a));
+        out.push(Sprite::new(x1, y, radius, r, g, b, a));
+    }
+}
@@ -0,0 +1,180 @@
+/// Particles for every effect, stored as parallel arrays.
+///
+/// Capacity is fixed at the global particle budget and allocated once, so
+/// spawning and culling never allocate during a frame.
+pub struct ParticlePool {
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
+    pub vx: Vec<f64>,
+    pub vy: Vec<f64>,
+    pub life: Vec<f64>,
+    pub max_life: Vec<f64>,
+    pub size: Vec<f64>,
+    pub start_size: Vec<f64>,
+    pub heat: Vec<f64>,
+    pub wobble: Vec<f64>,
+    /// Id of the effect instance that spawned each particle
+    pub owner: Vec<u32>,
+    /// Effect and emitter index in the effect library
+    pub source: Vec<(u16, u16)>,
+    capacity: usize,
+}
+
+/// Initial state of a new particle
+pub struct Spawn {
+    pub source: (u16, u16),
+    pub x: f64,
+    pub y: f64,
+    pub vx: f64,
+    pub vy: f64,
+    pub max_life: f64,
+    pub size: f64,
+    pub heat: f64,
+    pub wobble: f64,
+}
+
+impl ParticlePool {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            x: Vec::with_capacity(capacity),
+            y: Vec::with_capacity(capacity),
+            vx: Vec::with_capacity(capacity),
+            vy: Vec::with_capacity(capacity),
+            life: Vec::with_capacity(capacity),
+            max_life: Vec::with_capacity(capacity),
+            size: Vec::with_capacity(capacity),
+            start_size: Vec::with_capacity(capacity),
+            heat: Vec::with_capacity(capacity),
+            wobble: Vec::with_capacity(capacity),
+            owner: Vec::with_capacity(capacity),
+            source: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+
+    /// Add a particle; returns false once the pool is full
+    pub fn push(&mut self, owner: u32, p: Spawn) -> bool {
+        if self.len() >= self.capacity {
+            return false;
+        }
+        self.x.push(p.x);
+        self.y.push(p.y);
+        self.vx.push(p.vx);
+        self.vy.push(p.vy);
+        self.life.push(1.0);
+        self.max_life.push(p.max_life);
+        self.size.push(p.size);
+        self.start_size.push(p.size);
+        self.heat.push(p.heat);
+        self.wobble.push(p.wobble);
+        self.owner.push(owner);
+        self.source.push(p.source);
+        true
+    }
+
+    /// Copy particle `from` over particle `to`
+    fn move_to(&mut self, from: usize, to: usize) {
+        self.x[to] = self.x[from];
+        self.y[to] = self.y[from];
+        self.vx[to] = self.vx[from];
+        self.vy[to] = self.vy[from];
+        self.life[to] = self.life[from];
+        self.max_life[to] = self.max_life[from];
+        self.size[to] = self.size[from];
+        self.start_size[to] = self.start_size[from];
+        self.heat[to] = self.heat[from];
+        self.wobble[to] = self.wobble[from];
+        self.owner[to] = self.owner[from];
+        self.source[to] = self.source[from];
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.x.truncate(len);
+        self.y.truncate(len);
+        self.vx.truncate(len);
+        self.vy.truncate(len);
+        self.life.truncate(len);
+        self.max_life.truncate(len);
+        self.size.truncate(len);
+        self.start_size.truncate(len);
+        self.heat.truncate(len);
+        self.wobble.truncate(len);
+        self.owner.truncate(len);
+        self.source.truncate(len);
+    }
+
+    /// Drop every particle for which `keep` returns false. Survivors keep
+    /// their order, which is also their drawing order, so overlapping
+    /// particles don't swap places from one frame to the next.
+    pub fn retain<F: Fn(&Self, usize) -> bool>(&mut self, keep: F) {
+        let mut kept = 0;
+        for i in 0..self.len() {
+            if keep(self, i) {
+                if kept != i {
+                    self.move_to(i, kept);
+                }
+                kept += 1;
+            }
+        }
+        self.truncate(kept);
+    }
+
+    /// Indices of the particles owned by one effect
+    pub fn owned_by(&self, owner: u32) -> impl Iterator<Item = usize> + '_ {
+        self.owner
+            .iter()
+            .enumerate()
+            .filter(move |(_, &o)| o == owner)
+            .map(|(i, _)| i)
+    }
+
+    pub fn count(&self, owner: u32) -> usize {
+        self.owner.iter().filter(|&&o| o == owner).count()
+    }
+}
+
+/// Split `total` particles between effects wanting at most `caps[i]` each,
+/// max-min fairly: small effects get everything they ask for and the rest
+/// is shared evenly among the larger ones. Results are written to `shares`.
+pub fn fair_shares(total: usize, caps: &[usize], shares: &mut [usize]) {
+    let mut remaining = total;
+    let mut unsatisfied = caps.len();
+
+    for share in shares.iter_mut() {
+        *share = 0;
+    }
+
+    // Each round hands out an equal slice; effects that hit their cap drop out
+    while remaining > 0 && unsatisfied > 0 {
+        let slice = (remaining / unsatisfied).max(1);
+        let mut progressed = false;
+
+        for (share, &cap) in shares.iter_mut().zip(caps) {
+            if remaining == 0 {
+                break;
+            }
+            if *share >= cap {
+                continue;
+            }
+            let grant = slice.min(cap - *share).min(remaining);
+            *share += grant;
+            remaining -= grant;
+            progressed = true;
+            if *share >= cap {
+                unsatisfied -= 1;
+            }
+        }
+
+        if !progressed {
+            break;
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+/// Weight of the newest frame in the rolling average
+const SMOOTHING: f64 = 0.1;
+/// Step down this much when frames run over budget...
+const STEP_DOWN: f64 = 0.1;
+/// ...and creep back up this much when there is headroom
+const STEP_UP: f64 = 0.05;
+/// Frames to wait after a change before stepping down / up again
+const DOWN_COOLDOWN: u32 = 10;
+const UP_COOLDOWN: u32 = 60;
+/// Only step up while frames use less than this fraction of the budget
+const HEADROOM: f64 = 0.6;
+/// Extra glow passes are skipped below this level
+const GLOW_THRESHOLD: f64 = 0.5;
+
+/// Scales effect detail to keep tick + draw time within a frame budget.
+///
+/// The level runs from `min` to `max` (1.0 is full quality) and multiplies
+/// the particle budget and spawn rates; low levels also drop glow passes.
+pub struct Quality {
+    level: f64,
+    min: f64,
+    max: f64,
+    budget: f64,
+    // Rolling average of per-frame work, in seconds
+    average: f64,
+    // Work measured so far in the current frame
+    work: f64,
+    frames_since_change: u32,
+}
+
+impl Quality {
+    pub fn new(min: f64, max: f64, budget_ms: f64) -> Self {
+        let max = max.clamp(0.0, 1.0);
+        let min = min.clamp(0.0, max);
+        Self {
+            level: max,
+            min,
+            max,
+            budget: budget_ms.max(0.1) / 1000.0,
+            average: 0.0,
+            work: 0.0,
+            frames_since_change: 0,
+        }
+    }
+
+    /// Current level, between the configured floor and ceiling
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    /// Rolling average of tick + draw time per frame, in milliseconds
+    pub fn frame_ms(&self) -> f64 {
+        self.average * 1000.0
+    }
+
+    /// Whether optional glow passes should be drawn
+    pub fn glow(&self) -> bool {
+        self.level >= GLOW_THRESHOLD
+    }
+
+    /// Count time spent ticking or drawing towards the current frame
+    pub fn add_work(&mut self, elapsed: Duration) {
+        self.work += elapsed.as_secs_f64();
+    }
+
+    /// Close the current frame and adjust the level.
+    /// Returns true when the level changed.
+    pub fn end_frame(&mut self) -> bool {
+        self.average += (self.work - self.average) * SMOOTHING;
+        self.work = 0.0;
+        self.frames_since_change = self.frames_since_change.saturating_add(1);
+
+        let target = if self.average > self.budget && self.frames_since_change >= DOWN_COOLDOWN {
+            self.level - STEP_DOWN
+        } else if self.average < self.budget * HEADROOM && self.frames_since_change >= UP_COOLDOWN {
+            self.level + STEP_UP
+        } else {
+            return false;
+        };
+
+        let target = target.clamp(self.min, self.max);
+        if (target - self.level).abs() < f64::EPSILON {
+            return false;
+        }
+        self.level = target;
+        self.frames_since_change = 0;
+        true
+    }
+}
@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::collections::VecDeque;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::touch::{TouchDecoder, TouchEvent, ABS_MT_POSITION_X, ABS_MT_POSITION_Y};
+
+/// One line of a flick touch recording (JSON lines)
+#[derive(Serialize, Deserialize)]
+struct RecordedEvent {
+    /// Seconds since recording started
+    t: f64,
+    /// Simulation steps run before the event was handled; missing in
+    /// recordings made before replays were step-exact
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    step: Option<u64>,
+    kind: RecordedKind,
+    x: f64,
+    y: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RecordedKind {
+    Start,
+    Move,
+    End,
+}
+
+impl RecordedEvent {
+    fn new(t: f64, step: u64, event: TouchEvent) -> Self {
+        let (kind, x, y) = match event {
+            TouchEvent::Start(x, y) => (RecordedKind::Start, x, y),
+            TouchEvent::Move(x, y) => (RecordedKind::Move, x, y),
+            TouchEvent::End(x, y) => (RecordedKind::End, x, y),
+        };
+        Self { t, step: Some(step), kind, x, y }
+    }
+
+    fn event(&self) -> TouchEvent {
+        match self.kind {
+            RecordedKind::Start => TouchEvent::Start(self.x, self.y),
+            RecordedKind::Move => TouchEvent::Move(self.x, self.y),
+            RecordedKind::End => TouchEvent::End(self.x, self.y),
+        }
+    }
+}
+
+// ============ RECORDING ============
+
+/// Writes the TouchEvent stream to a file so a session can be replayed later
+pub struct Recorder {
+    out: BufWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let file = File::create(path)?;
+        println!("Recording touch events to {:?}", path);
+        Ok(Self {
+            out: BufWriter::new(file),
+            start: Instant::now(),
+        })
+    }
+
+    /// Write `event`, handled after `step` simulation steps
+    pub fn record(&mut self, event: TouchEvent, step: u64) {
+        let line = RecordedEvent::new(self.start.elapsed().as_secs_f64(), step, event);
+        if let Ok(json) = serde_json::to_string(&line) {
+            // Flush per event so a crash still leaves a usable recording
+            if writeln!(self.out, "{}", json).and_then(|_| self.out.flush()).is_err() {
+                eprintln!("Failed to write touch recording");
+            }
+        }
+    }
+}
+
+// ============ REPLAY ============
+
+/// Recorded events, fed into the simulation at the step each was recorded
+/// at rather than by wall-clock time, so a replay with the same seed
+/// matches the original exactly.
+///
+/// Accepts flick recordings as well as `evemu-record` and
+/// `libinput record` captures of a touchscreen, whose timestamps are
+/// rounded to steps of `sim_step` seconds.
+pub struct Replay {
+    events: VecDeque<(u64, TouchEvent)>,
+}
+
+impl Replay {
+    pub fn load(path: &Path, sim_step: f64) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        let events = parse_recording(&content, sim_step)?;
+        println!("Replaying {} touch events from {:?}", events.len(), path);
+        Ok(Self { events: events.into() })
+    }
+
+    /// Events recorded once `step` simulation steps had run, or earlier
+    pub fn take_due(&mut self, step: u64) -> Vec<TouchEvent> {
+        let mut due = Vec::new();
+        while self.events.front().is_some_and(|&(at, _)| at <= step) {
+            due.extend(self.events.pop_front().map(|(_, event)| event));
+        }
+        if !due.is_empty() && self.events.is_empty() {
+            println!("Touch replay finished");
+        }
+        due
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+fn parse_recording(content: &str, sim_step: f64) -> Result<Vec<(u64, TouchEvent)>, Box<dyn std::error::Error>> {
+    let first = content.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    let in_steps = |events: Vec<(f64, TouchEvent)>| events.into_iter().map(|(t, e)| (to_step(t, sim_step), e)).collect();
+
+    if first.starts_with('{') {
+        parse_flick(content, sim_step)
+    } else if first.starts_with("# EVEMU") || content.lines().any(|l| l.starts_with("E: ")) {
+        Ok(in_steps(parse_evemu(content)))
+    } else if content.lines().any(|l| l.trim_start().starts_with("evdev:")) {
+        Ok(in_steps(parse_libinput(content)))
+    } else {
+        Err("Unrecognized recording format".into())
+    }
+}
+
+fn to_step(t: f64, sim_step: f64) -> u64 {
+    (t.max(0.0) / sim_step).round() as u64
+}
+
+fn parse_flick(content: &str, sim_step: f64) -> Result<Vec<(u64, TouchEvent)>, Box<dyn std::error::Error>> {
+    let mut events = Vec::new();
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let recorded: RecordedEvent = serde_json::from_str(line)?;
+        let step = recorded.step.unwrap_or_else(|| to_step(recorded.t, sim_step));
+        events.push((step, recorded.event()));
+    }
+    Ok(events)
+}
+
+/// Decode raw evdev events (seconds, type, code, value) with the same slot
+/// logic as the live monitor, rebasing timestamps to start at zero
+fn decode_raw(raw: &[(f64, u16, u16, i32)], abs_x_max: i32, abs_y_max: i32) -> Vec<(f64, TouchEvent)> {
+    let mut decoder = TouchDecoder::new(abs_x_max, abs_y_max);
+    let mut events = Vec::new();
+    let t0 = raw.first().map(|e| e.0).unwrap_or(0.0);
+
+    for &(t, ev_type, ev_code, ev_value) in raw {
+        decoder.feed(ev_type, ev_code, ev_value, &mut |event| events.push((t - t0, event)));
+    }
+    events
+}
+
+/// `evemu-record` output:
+///   A: 35 0 1079 0 0 0
+///   E: 12.345678 0003 0035 0412   # EV_ABS / ABS_MT_POSITION_X
+fn parse_evemu(content: &str) -> Vec<(f64, TouchEvent)> {
+    let mut abs_x_max = 1080;
+    let mut abs_y_max = 2340;
+    let mut raw = Vec::new();
+
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["A:", code, _min, max, ..] => {
+                let code = u16::from_str_radix(code, 16).ok();
+                let max = max.parse::<i32>().ok();
+                match (code, max) {
+                    (Some(ABS_MT_POSITION_X), Some(max)) => abs_x_max = max,
+                    (Some(ABS_MT_POSITION_Y), Some(max)) => abs_y_max = max,
+                    _ => {}
+                }
+            }
+            ["E:", time, ev_type, ev_code, ev_value, ..] => {
+                let parsed = (
+                    time.parse::<f64>(),
+                    u16::from_str_radix(ev_type, 16),
+                    u16::from_str_radix(ev_code, 16),
+                    ev_value.parse::<i32>(),
+                );
+                if let (Ok(t), Ok(ty), Ok(code), Ok(value)) = parsed {
+                    raw.push((t, ty, code, value));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    decode_raw(&raw, abs_x_max, abs_y_max)
+}
+
+/// `libinput record` output (YAML), read line by line:
+///   absinfo:
+///     53: [0, 1079, 0, 0, 0]
+///   events:
+///   - evdev:
+///     - [  0,  12345,   3,  53,   412] # EV_ABS / ABS_MT_POSITION_X
+fn parse_libinput(content: &str) -> Vec<(f64, TouchEvent)> {
+    let mut abs_x_max = 1080;
+    let mut abs_y_max = 2340;
+    let mut raw = Vec::new();
+
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let line = line.trim_start_matches('-').trim();
+
+        let Some(open) = line.find('[') else { continue };
+        let Some(close) = line.find(']') else { continue };
+        let values: Vec<i64> = line[open + 1..close]
+            .split(',')
+            .filter_map(|v| v.trim().parse().ok())
+            .collect();
+
+        if open == 0 && values.len() == 5 {
+            let t = values[0] as f64 + values[1] as f64 / 1_000_000.0;
+            raw.push((t, values[2] as u16, values[3] as u16, values[4] as i32));
+        } else if let Some(code) = line[..open].trim().strip_suffix(':') {
+            // absinfo entry: code: [min, max, fuzz, flat, resolution]
+            match (code.parse::<u16>(), values.get(1)) {
+                (Ok(ABS_MT_POSITION_X), Some(&max)) => abs_x_max = max as i32,
+                (Ok(ABS_MT_POSITION_Y), Some(&max)) => abs_y_max = max as i32,
+                _ => {}
+            }
+        }
+    }
+
+    decode_raw(&raw, abs_x_max, abs_y_max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIM_STEP: f64 = 1.0 / 60.0;
+
+    /// Two fingers: slot 0 lands and moves, slot 1 lands, then slot 0 and
+    /// slot 1 lift in turn
+    const EVEMU: &str = "\
+# EVEMU 1.3
+# Input device name: \"test touchscreen\"
+A: 35 0 1024 0 0 0
+A: 36 0 2048 0 0 0
+E: 10.000000 0003 002f 0\t# EV_ABS / ABS_MT_SLOT
+E: 10.000000 0003 0039 1\t# EV_ABS / ABS_MT_TRACKING_ID
+E: 10.000000 0003 0035 200\t# EV_ABS / ABS_MT_POSITION_X
+E: 10.000000 0003 0036 400\t# EV_ABS / ABS_MT_POSITION_Y
+E: 10.000000 0000 0000 0\t# ------------ SYN_REPORT (0) ----------
+E: 10.050000 0003 0035 220
+E: 10.050000 0000 0000 0
+E: 10.100000 0003 002f 1
+E: 10.100000 0003 0039 2
+E: 10.100000 0003 0035 600
+E: 10.100000 0003 0036 1000
+E: 10.100000 0000 0000 0
+E: 10.150000 0003 002f 0
+E: 10.150000 0003 0039 -1
+E: 10.150000 0000 0000 0
+E: 10.200000 0003 002f 1
+E: 10.200000 0003 0039 -1
+E: 10.200000 0000 0000 0
+";
+
+    /// The same touches as EVEMU
+    const LIBINPUT: &str = "\
+version: 1
+ndevices: 1
+devices:
+- node: /dev/input/event1
+  evdev:
+    name: \"test touchscreen\"
+    absinfo:
+      53: [0, 1024, 0, 0, 0]
+      54: [0, 2048, 0, 0, 0]
+  events:
+  - evdev:
+    - [  0,      0,   3,  47,     0] # EV_ABS / ABS_MT_SLOT
+    - [  0,      0,   3,  57,     1] # EV_ABS / ABS_MT_TRACKING_ID
+    - [  0,      0,   3,  53,   200] # EV_ABS / ABS_MT_POSITION_X
+    - [  0,      0,   3,  54,   400] # EV_ABS / ABS_MT_POSITION_Y
+    - [  0,      0,   0,   0,     0] # ------------ SYN_REPORT (0) ----------
+  - evdev:
+    - [  0,  50000,   3,  53,   220]
+    - [  0,  50000,   0,   0,     0]
+  - evdev:
+    - [  0, 100000,   3,  47,     1]
+    - [  0, 100000,   3,  57,     2]
+    - [  0, 100000,   3,  53,   600]
+    - [  0, 100000,   3,  54,  1000]
+    - [  0, 100000,   0,   0,     0]
+  - evdev:
+    - [  0, 150000,   3,  47,     0]
+    - [  0, 150000,   3,  57,    -1]
+    - [  0, 150000,   0,   0,     0]
+  - evdev:
+    - [  0, 200000,   3,  47,     1]
+    - [  0, 200000,   3,  57,    -1]
+    - [  0, 200000,   0,   0,     0]
+";
+
+    fn summary(events: Vec<(u64, TouchEvent)>) -> Vec<(u64, &'static str, f64, f64)> {
+        events
+            .into_iter()
+            .map(|(step, event)| match event {
+                TouchEvent::Start(x, y) => (step, "start", x, y),
+                TouchEvent::Move(x, y) => (step, "move", x, y),
+                TouchEvent::End(x, y) => (step, "end", x, y),
+            })
+            .collect()
+    }
+
+    /// Device units are halved into logical pixels. A new touch starts
+    /// where its slot last was, before its position arrives.
+    fn expected() -> Vec<(u64, &'static str, f64, f64)> {
+        vec![
+            (0, "start", 0.0, 0.0),
+            (0, "move", 100.0, 0.0),
+            (0, "move", 100.0, 200.0),
+            (3, "move", 110.0, 200.0),
+            (6, "start", 0.0, 0.0),
+            (6, "move", 300.0, 0.0),
+            (6, "move", 300.0, 500.0),
+            (9, "end", 110.0, 200.0),
+            (12, "end", 300.0, 500.0),
+        ]
+    }
+
+    #[test]
+    fn evemu_record() {
+        let events = parse_recording(EVEMU, SIM_STEP).expect("evemu is recognized");
+        assert_eq!(summary(events), expected());
+    }
+
+    #[test]
+    fn libinput_record() {
+        let events = parse_recording(LIBINPUT, SIM_STEP).expect("libinput is recognized");
+        assert_eq!(summary(events), expected());
+    }
+
+    #[test]
+    fn unknown_format() {
+        assert!(parse_recording("hello\n", SIM_STEP).is_err());
+    }
+}
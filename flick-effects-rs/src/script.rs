@@ -0,0 +1,524 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::bounds::Bounds;
+use crate::emitter::EffectLibrary;
+use crate::sprites::GLOW_SCALE;
+
+/// Draw calls kept per script per frame; the rest are dropped
+const MAX_COMMANDS: usize = 2048;
+/// Largest radius / line width a script may draw with
+const MAX_RADIUS: f64 = 512.0;
+const MAX_LINE_WIDTH: f64 = 64.0;
+/// Errors or timeouts in a row before a script is switched off
+const MAX_FAILURES: u32 = 3;
+/// Operations allowed per call, in case the clock check never gets to run
+const MAX_OPERATIONS: u64 = 5_000_000;
+/// How often the battery level is re-read, in simulation seconds
+const BATTERY_POLL: f64 = 30.0;
+
+#[derive(Clone, Copy)]
+pub enum Shape {
+    /// Soft sprite glow, like the particle effects
+    Glow { x: f64, y: f64, radius: f64 },
+    Circle { x: f64, y: f64, radius: f64 },
+    Line { x0: f64, y0: f64, x1: f64, y1: f64, width: f64 },
+}
+
+impl Shape {
+    pub fn center(&self) -> (f64, f64) {
+        match *self {
+            Shape::Glow { x, y, .. } | Shape::Circle { x, y, .. } => (x, y),
+            Shape::Line { x0, y0, x1, y1, .. } => ((x0 + x1) / 2.0, (y0 + y1) / 2.0),
+        }
+    }
+}
+
+/// One recorded call from a script's draw()
+#[derive(Clone, Copy)]
+pub struct DrawCommand {
+    pub shape: Shape,
+    pub color: (f64, f64, f64),
+    pub alpha: f64,
+}
+
+/// Values scripts can read through the environment functions
+struct ScriptEnv {
+    time: f64,
+    width: f64,
+    height: f64,
+    quality: f64,
+    battery: f64,
+    reduce_motion: bool,
+    rng: StdRng,
+    // Set by color(), used by the following shapes
+    color: (f64, f64, f64),
+    alpha: f64,
+}
+
+/// Per-frame inputs handed to every script
+pub struct Frame {
+    pub time: f64,
+    pub width: f64,
+    pub height: f64,
+    pub quality: f64,
+    // Scripts should keep still: no large or fast movement
+    pub reduce_motion: bool,
+}
+
+/// A Rhai script implementing any of `init()`, `on_touch(kind, x, y)`,
+/// `tick(dt)` and `draw()`. State lives in the map returned by init() and
+/// is reachable as `this`. Scripts only see the drawing and environment
+/// functions registered here, and every call is cut off after a time limit.
+pub struct ScriptEffect {
+    pub name: String,
+    engine: Engine,
+    ast: AST,
+    this: Dynamic,
+    env: Rc<RefCell<ScriptEnv>>,
+    commands: Rc<RefCell<Vec<DrawCommand>>>,
+    deadline: Rc<Cell<Option<Instant>>>,
+    time_limit: Duration,
+    has_touch: bool,
+    has_tick: bool,
+    has_draw: bool,
+    animating: bool,
+    failures: u32,
+    disabled: bool,
+}
+
+impl ScriptEffect {
+    pub fn load(path: &Path, time_limit: Duration, rng: StdRng) -> Result<Self, String> {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+        let env = Rc::new(RefCell::new(ScriptEnv {
+            time: 0.0,
+            width: 0.0,
+            height: 0.0,
+            quality: 1.0,
+            battery: -1.0,
+            reduce_motion: false,
+            rng,
+            color: (1.0, 1.0, 1.0),
+            alpha: 1.0,
+        }));
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let deadline: Rc<Cell<Option<Instant>>> = Rc::new(Cell::new(None));
+        let engine = build_engine(&name, &env, &commands, &deadline);
+
+        let ast = engine.compile(&source).map_err(|e| e.to_string())?;
+        let has = |f: &str| ast.iter_functions().any(|m| m.name == f);
+        let (has_init, has_touch, has_tick, has_draw) = (has("init"), has("on_touch"), has("tick"), has("draw"));
+
+        let mut script = Self {
+            name,
+            engine,
+            ast,
+            this: Dynamic::from_map(Map::new()),
+            env,
+            commands,
+            deadline,
+            time_limit,
+            has_touch,
+            has_tick,
+            has_draw,
+            animating: true,
+            failures: 0,
+            disabled: false,
+        };
+
+        // Top-level statements run once here, along with init()
+        script.deadline.set(Some(Instant::now() + time_limit));
+        let result = if has_init {
+            let options = CallFnOptions::new().eval_ast(true);
+            script
+                .engine
+                .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &script.ast, "init", ())
+        } else {
+            script.engine.run_ast(&script.ast).map(|_| Dynamic::UNIT)
+        };
+        script.deadline.set(None);
+
+        match result {
+            Ok(state) if !state.is_unit() => script.this = state,
+            Ok(_) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+        Ok(script)
+    }
+
+    /// Call a script function with `this` bound, under the time limit
+    fn call(&mut self, function: &str, args: impl rhai::FuncArgs) -> Option<Dynamic> {
+        if self.disabled {
+            return None;
+        }
+
+        self.deadline.set(Some(Instant::now() + self.time_limit));
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.this);
+        let result = self
+            .engine
+            .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, function, args);
+        self.deadline.set(None);
+
+        match result {
+            Ok(value) => {
+                self.failures = 0;
+                Some(value)
+            }
+            Err(e) => {
+                eprintln!("Script '{}' {}() failed: {}", self.name, function, e);
+                self.failures += 1;
+                if self.failures >= MAX_FAILURES {
+                    eprintln!("Disabling script '{}'", self.name);
+                    self.disabled = true;
+                    self.animating = false;
+                    self.commands.borrow_mut().clear();
+                }
+                None
+            }
+        }
+    }
+
+    /// `kind` is "down", "move" or "up"
+    pub fn on_touch(&mut self, kind: &str, x: f64, y: f64) {
+        if self.disabled {
+            return;
+        }
+        self.animating = true;
+        if self.has_touch {
+            self.call("on_touch", (kind.to_string(), x, y));
+        }
+    }
+
+    /// Advance the script and record this frame's drawing.
+    /// A tick() returning `false` lets the overlay sleep until the next touch.
+    pub fn tick(&mut self, dt: f64, frame: &Frame, battery: f64) {
+        if self.disabled || !self.animating {
+            return;
+        }
+        {
+            let mut env = self.env.borrow_mut();
+            env.time = frame.time;
+            env.width = frame.width;
+            env.height = frame.height;
+            env.quality = frame.quality;
+            env.battery = battery;
+            env.reduce_motion = frame.reduce_motion;
+        }
+
+        if self.has_tick {
+            if let Some(result) = self.call("tick", (dt,)) {
+                self.animating = result.as_bool().unwrap_or(true);
+            }
+        }
+
+        self.commands.borrow_mut().clear();
+        if self.has_draw {
+            {
+                let mut env = self.env.borrow_mut();
+                env.color = (1.0, 1.0, 1.0);
+                env.alpha = 1.0;
+            }
+            self.call("draw", ());
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animating
+    }
+
+    pub fn commands(&self) -> std::cell::Ref<'_, Vec<DrawCommand>> {
+        self.commands.borrow()
+    }
+
+    /// Screen area covered by the last recorded frame
+    pub fn bounds(&self) -> Bounds {
+        let mut bounds = Bounds::empty();
+        for command in self.commands.borrow().iter() {
+            match command.shape {
+                Shape::Glow { x, y, radius } => bounds.include(x, y, radius * GLOW_SCALE),
+                Shape::Circle { x, y, radius } => bounds.include(x, y, radius),
+                Shape::Line { x0, y0, x1, y1, width } => {
+                    bounds.include(x0, y0, width / 2.0);
+                    bounds.include(x1, y1, width / 2.0);
+                }
+            }
+        }
+        bounds
+    }
+}
+
+/// Sandboxed engine: no eval or imports, bounded recursion, container
+/// sizes and operation count, and a progress hook that aborts once
+/// `deadline` passes
+fn build_engine(
+    name: &str,
+    env: &Rc<RefCell<ScriptEnv>>,
+    commands: &Rc<RefCell<Vec<DrawCommand>>>,
+    deadline: &Rc<Cell<Option<Instant>>>,
+) -> Engine {
+    let mut engine = Engine::new();
+    engine.disable_symbol("eval");
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(10_000);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(1_000);
+
+    let deadline = deadline.clone();
+    engine.on_progress(move |ops| {
+        // Checking the clock on every operation would dominate cheap scripts
+        if ops % 256 != 0 {
+            return None;
+        }
+        match deadline.get() {
+            Some(deadline) if Instant::now() > deadline => Some(Dynamic::from("time limit exceeded")),
+            _ => None,
+        }
+    });
+
+    let prefix = name.to_string();
+    engine.on_print(move |s| println!("[{}] {}", prefix, s));
+
+    // Environment
+    let e = env.clone();
+    engine.register_fn("time", move || e.borrow().time);
+    let e = env.clone();
+    engine.register_fn("screen_width", move || e.borrow().width);
+    let e = env.clone();
+    engine.register_fn("screen_height", move || e.borrow().height);
+    let e = env.clone();
+    engine.register_fn("quality", move || e.borrow().quality);
+    let e = env.clone();
+    engine.register_fn("battery", move || e.borrow().battery);
+    let e = env.clone();
+    engine.register_fn("reduce_motion", move || e.borrow().reduce_motion);
+    let e = env.clone();
+    engine.register_fn("random", move || e.borrow_mut().rng.gen::<f64>());
+
+    // Drawing
+    let e = env.clone();
+    engine.register_fn("color", move |r: Dynamic, g: Dynamic, b: Dynamic, a: Dynamic| {
+        let mut env = e.borrow_mut();
+        env.color = (unit(&r), unit(&g), unit(&b));
+        env.alpha = unit(&a);
+    });
+
+    let (e, c) = (env.clone(), commands.clone());
+    engine.register_fn("glow", move |x: Dynamic, y: Dynamic, radius: Dynamic| {
+        let (x, y) = (num(&x), num(&y));
+        let radius = num(&radius).clamp(0.0, MAX_RADIUS);
+        record(&e, &c, Shape::Glow { x, y, radius });
+    });
+
+    let (e, c) = (env.clone(), commands.clone());
+    engine.register_fn("circle", move |x: Dynamic, y: Dynamic, radius: Dynamic| {
+        let (x, y) = (num(&x), num(&y));
+        let radius = num(&radius).clamp(0.0, MAX_RADIUS);
+        record(&e, &c, Shape::Circle { x, y, radius });
+    });
+
+    let (e, c) = (env.clone(), commands.clone());
+    engine.register_fn("line", move |x0: Dynamic, y0: Dynamic, x1: Dynamic, y1: Dynamic, width: Dynamic| {
+        let (x0, y0, x1, y1) = (num(&x0), num(&y0), num(&x1), num(&y1));
+        let width = num(&width).clamp(0.0, MAX_LINE_WIDTH);
+        record(&e, &c, Shape::Line { x0, y0, x1, y1, width });
+    });
+
+    engine
+}
+
+/// Queue a shape in the current color, dropping anything non-finite
+fn record(env: &Rc<RefCell<ScriptEnv>>, commands: &Rc<RefCell<Vec<DrawCommand>>>, shape: Shape) {
+    let finite = match shape {
+        Shape::Glow { x, y, radius } | Shape::Circle { x, y, radius } => {
+            x.is_finite() && y.is_finite() && radius.is_finite()
+        }
+        Shape::Line { x0, y0, x1, y1, width } => [x0, y0, x1, y1, width].iter().all(|v| v.is_finite()),
+    };
+    let mut commands = commands.borrow_mut();
+    if !finite || commands.len() >= MAX_COMMANDS {
+        return;
+    }
+    let env = env.borrow();
+    commands.push(DrawCommand {
+        shape,
+        color: env.color,
+        alpha: env.alpha,
+    });
+}
+
+/// Scripts may pass integers or floats
+fn num(value: &Dynamic) -> f64 {
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|i| i as f64))
+        .unwrap_or(f64::NAN)
+}
+
+fn unit(value: &Dynamic) -> f64 {
+    let v = num(value);
+    if v.is_finite() { v.clamp(0.0, 1.0) } else { 0.0 }
+}
+
+/// Scripted effects enabled in the config, plus the shared inputs they read
+pub struct ScriptHost {
+    scripts: Vec<ScriptEffect>,
+    battery: f64,
+    // Simulation time since the battery was last read
+    battery_age: f64,
+}
+
+impl ScriptHost {
+    /// Load `<name>.rhai` from the effects directory for each name.
+    /// Each script gets its own RNG seeded from `rng`.
+    pub fn load(names: &[String], time_limit_ms: f64, rng: &mut StdRng) -> Self {
+        let dir = EffectLibrary::effects_dir();
+        let time_limit = Duration::from_secs_f64(time_limit_ms.max(0.1) / 1000.0);
+        let mut scripts = Vec::new();
+
+        for name in names {
+            let path = dir.join(format!("{}.rhai", name));
+            let script_rng = rand::SeedableRng::seed_from_u64(rng.gen());
+            match ScriptEffect::load(&path, time_limit, script_rng) {
+                Ok(script) => {
+                    println!("Loaded script {:?}", path);
+                    scripts.push(script);
+                }
+                Err(e) => eprintln!("Skipping script {:?}: {}", path, e),
+            }
+        }
+
+        Self {
+            scripts,
+            battery: read_battery(),
+            battery_age: 0.0,
+        }
+    }
+
+    pub fn scripts(&self) -> &[ScriptEffect] {
+        &self.scripts
+    }
+
+    pub fn on_touch(&mut self, kind: &str, x: f64, y: f64) {
+        for script in &mut self.scripts {
+            script.on_touch(kind, x, y);
+        }
+    }
+
+    pub fn tick(&mut self, dt: f64, frame: &Frame) {
+        if self.scripts.is_empty() {
+            return;
+        }
+
+        self.battery_age += dt;
+        if self.battery_age >= BATTERY_POLL {
+            self.battery = read_battery();
+            self.battery_age = 0.0;
+        }
+
+        for script in &mut self.scripts {
+            script.tick(dt, frame, self.battery);
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.scripts.iter().any(|s| s.is_animating())
+    }
+}
+
+/// Battery charge in percent, or -1 when there is no battery
+fn read_battery() -> f64 {
+    let Ok(entries) = fs::read_dir("/sys/class/power_supply") else { return -1.0 };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_battery = fs::read_to_string(path.join("type")).is_ok_and(|t| t.trim() == "Battery");
+        if !is_battery {
+            continue;
+        }
+        if let Some(capacity) = fs::read_to_string(path.join("capacity"))
+            .ok()
+            .and_then(|c| c.trim().parse::<f64>().ok())
+        {
+            return capacity;
+        }
+    }
+    -1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const DT: f64 = 1.0 / 60.0;
+    const FRAME: Frame = Frame { time: 0.0, width: 540.0, height: 1170.0, quality: 1.0, reduce_motion: false };
+
+    /// Load `source` as script `name` with the given per-call time limit
+    fn load(name: &str, source: &str, time_limit: Duration) -> Result<ScriptEffect, String> {
+        let path = std::env::temp_dir().join(format!("{}-{}.rhai", name, std::process::id()));
+        fs::write(&path, source).expect("write test script");
+        let script = ScriptEffect::load(&path, time_limit, StdRng::seed_from_u64(1));
+        fs::remove_file(&path).ok();
+        script
+    }
+
+    #[test]
+    fn endless_tick_is_cut_off_then_disabled() {
+        let mut script = load("endless", "fn tick(dt) { loop {} }", Duration::from_millis(20)).unwrap();
+        for failures in 1..=MAX_FAILURES {
+            let start = Instant::now();
+            script.tick(DT, &FRAME, -1.0);
+            assert!(start.elapsed() < Duration::from_secs(1), "tick ran {:?}", start.elapsed());
+            assert_eq!(script.failures, failures);
+            assert_eq!(script.disabled, failures == MAX_FAILURES);
+        }
+        assert!(!script.is_animating());
+
+        // Switched off for good: touches no longer wake it
+        script.on_touch("down", 10.0, 10.0);
+        assert!(!script.is_animating());
+    }
+
+    #[test]
+    fn operation_cap_stops_a_loop_the_clock_never_catches() {
+        let mut script = load("busy", "fn tick(dt) { loop {} }", Duration::from_secs(3600)).unwrap();
+        script.tick(DT, &FRAME, -1.0);
+        assert_eq!(script.failures, 1);
+    }
+
+    #[test]
+    fn imports_are_refused() {
+        assert!(load("import_top", "import \"os\" as os;", Duration::from_millis(20)).is_err());
+
+        let mut script = load("import_tick", "fn tick(dt) { import \"os\" as os; }", Duration::from_millis(20)).unwrap();
+        for _ in 0..MAX_FAILURES {
+            script.tick(DT, &FRAME, -1.0);
+        }
+        assert!(script.disabled);
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let source = "fn tick(dt) { if time() < 1.0 { throw \"early\"; } true }";
+        let mut script = load("flaky", source, Duration::from_millis(20)).unwrap();
+        for _ in 1..MAX_FAILURES {
+            script.tick(DT, &FRAME, -1.0);
+        }
+        script.tick(DT, &Frame { time: 2.0, ..FRAME }, -1.0);
+        assert_eq!(script.failures, 0);
+        assert!(!script.disabled);
+    }
+}
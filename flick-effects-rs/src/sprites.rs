@@ -0,0 +1,101 @@
+use gtk4::cairo::{Context, Format, ImageSurface, RadialGradient};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+/// Glow extends this far past the particle radius
+pub const GLOW_SCALE: f64 = 2.0;
+/// Sprite sizes step geometrically, so nearby radii share a sprite
+const SIZE_STEP: f64 = 1.15;
+/// Color channels are quantized to this many levels
+const COLOR_LEVELS: f64 = 15.0;
+/// Drop everything if the cache grows past this many sprites
+const MAX_SPRITES: usize = 512;
+
+/// Pre-rendered soft radial-gradient glows, blitted per particle instead of
+/// building and filling an arc path each time
+pub struct SpriteCache {
+    sprites: HashMap<(u16, u16), ImageSurface>,
+}
+
+impl SpriteCache {
+    pub fn new() -> Self {
+        Self {
+            sprites: HashMap::new(),
+        }
+    }
+
+    /// Draw a glowing particle of `radius` centred on (x, y)
+    pub fn draw(&mut self, cr: &Context, x: f64, y: f64, radius: f64, color: (f64, f64, f64), alpha: f64) {
+        if alpha <= 0.0 || radius <= 0.0 {
+            return;
+        }
+
+        let size_bucket = size_bucket(radius);
+        let color_bucket = color_bucket(color);
+        if self.sprites.len() >= MAX_SPRITES && !self.sprites.contains_key(&(color_bucket, size_bucket)) {
+            self.sprites.clear();
+        }
+
+        let sprite = self
+            .sprites
+            .entry((color_bucket, size_bucket))
+            .or_insert_with(|| render_sprite(bucket_radius(size_bucket), color));
+        let half = sprite.width() as f64 / 2.0;
+
+        // Scale the bucket's sprite to the exact radius, so the glow ends
+        // where the caller's bounds say it does
+        let scale = radius / bucket_radius(size_bucket);
+        cr.save().ok();
+        cr.translate(x, y);
+        cr.scale(scale, scale);
+        if cr.set_source_surface(sprite, -half, -half).is_ok() {
+            cr.paint_with_alpha(alpha.min(1.0)).ok();
+        }
+        cr.restore().ok();
+    }
+
+    /// Sprites rendered so far
+    pub fn cached(&self) -> usize {
+        self.sprites.len()
+    }
+}
+
+impl Default for SpriteCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn size_bucket(radius: f64) -> u16 {
+    (radius.max(0.5).ln() / SIZE_STEP.ln()).round().max(0.0) as u16
+}
+
+fn bucket_radius(bucket: u16) -> f64 {
+    SIZE_STEP.powi(bucket as i32)
+}
+
+fn color_bucket((r, g, b): (f64, f64, f64)) -> u16 {
+    let q = |c: f64| (c.clamp(0.0, 1.0) * COLOR_LEVELS).round() as u16;
+    (q(r) << 8) | (q(g) << 4) | q(b)
+}
+
+/// Solid core out to `radius`, fading to transparent at GLOW_SCALE * radius
+fn render_sprite(radius: f64, (r, g, b): (f64, f64, f64)) -> ImageSurface {
+    let half = (radius * GLOW_SCALE).ceil() + 1.0;
+    let size = (half * 2.0) as i32;
+    let surface = ImageSurface::create(Format::ARgb32, size, size).expect("Failed to create sprite surface");
+
+    if let Ok(cr) = Context::new(&surface) {
+        let extent = radius * GLOW_SCALE;
+        let gradient = RadialGradient::new(half, half, 0.0, half, half, extent);
+        gradient.add_color_stop_rgba(0.0, r, g, b, 1.0);
+        gradient.add_color_stop_rgba(0.4 / GLOW_SCALE, r, g, b, 0.9);
+        gradient.add_color_stop_rgba(1.0 / GLOW_SCALE, r, g, b, 0.45);
+        gradient.add_color_stop_rgba(1.0, r, g, b, 0.0);
+        cr.set_source(&gradient).ok();
+        cr.arc(half, half, extent, 0.0, 2.0 * PI);
+        cr.fill().ok();
+    }
+    surface.flush();
+    surface
+}
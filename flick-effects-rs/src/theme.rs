@@ -0,0 +1,76 @@
+use gio::prelude::*;
+use std::cell::Cell;
+use std::fs;
+use std::path::PathBuf;
+
+/// Shell theme written by the Flick settings app (ThemePage)
+pub fn display_config_path() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
+    PathBuf::from(home)
+        .join(".local")
+        .join("state")
+        .join("flick")
+        .join("display_config.json")
+}
+
+/// The theme's `accent_color`, if set and parseable
+pub fn read_accent() -> Option<[f64; 3]> {
+    let content = fs::read_to_string(display_config_path()).ok()?;
+    let config: serde_json::Value = serde_json::from_str(&content).ok()?;
+    parse_hex_color(config.get("accent_color")?.as_str()?)
+}
+
+/// Parse `#rgb`, `#rrggbb` or Qt's `#aarrggbb` (alpha is ignored)
+pub fn parse_hex_color(s: &str) -> Option<[f64; 3]> {
+    let hex = s.trim().strip_prefix('#')?;
+    if !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize, len: usize| {
+        let v = u8::from_str_radix(&hex[i..i + len], 16).ok()?;
+        Some(if len == 1 { v * 17 } else { v } as f64 / 255.0)
+    };
+    let (start, len) = match hex.len() {
+        3 => (0, 1),
+        6 => (0, 2),
+        8 => (2, 2),
+        _ => return None,
+    };
+    Some([
+        channel(start, len)?,
+        channel(start + len, len)?,
+        channel(start + 2 * len, len)?,
+    ])
+}
+
+/// Call `on_change` with the new accent whenever the display config is
+/// rewritten. Monitoring stops when the returned monitor is dropped.
+pub fn watch_accent<F>(on_change: F) -> Option<gio::FileMonitor>
+where
+    F: Fn([f64; 3]) + 'static,
+{
+    let file = gio::File::for_path(display_config_path());
+    let monitor = file
+        .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+        .map_err(|e| eprintln!("Cannot watch theme config: {}", e))
+        .ok()?;
+
+    let current = Cell::new(read_accent());
+    monitor.connect_changed(move |_, _, _, event| {
+        if !matches!(
+            event,
+            gio::FileMonitorEvent::Created | gio::FileMonitorEvent::Changed | gio::FileMonitorEvent::ChangesDoneHint
+        ) {
+            return;
+        }
+        // Partially written files fail to parse; a later event will catch up
+        if let Some(accent) = read_accent() {
+            if current.get() != Some(accent) {
+                current.set(Some(accent));
+                println!("Theme accent changed");
+                on_change(accent);
+            }
+        }
+    });
+    Some(monitor)
+}
@@ -1,30 +1,112 @@
-use std::fs::File;
+use glib::ControlFlow;
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
 use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+use std::sync::mpsc;
 use std::thread;
+use std::time::Duration;
 
+#[derive(Clone, Copy, Debug)]
 pub enum TouchEvent {
     Start(f64, f64),
     Move(f64, f64),
     End(f64, f64),
 }
 
+/// Reads touch input and delivers each event to a handler running on the
+/// GTK main loop. Must be created on the main thread.
 pub struct TouchMonitor {
-    _handle: thread::JoinHandle<()>,
+    // None when the device is read directly by the main loop
+    _handle: Option<thread::JoinHandle<()>>,
 }
 
 impl TouchMonitor {
-    pub fn new<F>(callback: F) -> Self
+    pub fn new<F>(handler: F) -> Self
     where
-        F: Fn(TouchEvent) + Send + 'static,
+        F: FnMut(TouchEvent) + 'static,
     {
+        let sender = attach_to_main_loop(handler);
         let handle = thread::spawn(move || {
-            if let Err(e) = monitor_touch(callback) {
+            if let Err(e) = monitor_touch(|event| sender.send(event)) {
                 eprintln!("Touch monitor error: {}", e);
             }
         });
 
-        TouchMonitor { _handle: handle }
+        TouchMonitor { _handle: Some(handle) }
     }
+
+    /// Read the touchscreen from a non-blocking fd source on the main loop,
+    /// without a helper thread
+    pub fn in_main_loop<F>(handler: F) -> Self
+    where
+        F: FnMut(TouchEvent) + 'static,
+    {
+        attach_device_source(Rc::new(RefCell::new(handler)));
+        TouchMonitor { _handle: None }
+    }
+}
+
+// ============ MAIN LOOP DELIVERY ============
+
+/// Thread-side half of the channel into the main loop
+pub struct EventSender<T> {
+    tx: mpsc::Sender<T>,
+    wake: UnixStream,
+}
+
+impl<T> EventSender<T> {
+    pub fn send(&self, event: T) {
+        if self.tx.send(event).is_ok() {
+            // A full socket already means a wakeup is pending
+            let _ = (&self.wake).write(&[1]);
+        }
+    }
+}
+
+/// Queue events for `handler` and watch a socket with a glib fd source, so
+/// the main loop wakes exactly when input arrives instead of polling.
+/// Also used for events from other threads, such as D-Bus filters.
+pub fn attach_to_main_loop<T, F>(mut handler: F) -> EventSender<T>
+where
+    T: 'static,
+    F: FnMut(T) + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let (wake_rx, wake_tx) = UnixStream::pair().expect("Failed to create wake socket");
+    wake_rx.set_nonblocking(true).ok();
+    wake_tx.set_nonblocking(true).ok();
+
+    // Removed once every sender is gone; the socket then reads as EOF
+    // forever and would keep waking the loop
+    glib::unix_fd_add_local(wake_rx.as_raw_fd(), glib::IOCondition::IN, move |_, _| {
+        let mut buf = [0u8; 64];
+        let mut closed = false;
+        loop {
+            match (&wake_rx).read(&mut buf) {
+                Ok(0) => {
+                    closed = true;
+                    break;
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+
+        loop {
+            match rx.try_recv() {
+                Ok(event) => handler(event),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return ControlFlow::Break,
+            }
+        }
+        if closed { ControlFlow::Break } else { ControlFlow::Continue }
+    });
+
+    EventSender { tx, wake: wake_tx }
 }
 
 fn find_touchscreen() -> Option<String> {
@@ -46,17 +128,37 @@ fn find_touchscreen() -> Option<String> {
     Some("/dev/input/event3".to_string())
 }
 
-fn monitor_touch<F>(callback: F) -> Result<(), Box<dyn std::error::Error>>
+fn monitor_touch<F>(mut callback: F) -> Result<(), Box<dyn std::error::Error>>
 where
-    F: Fn(TouchEvent),
+    F: FnMut(TouchEvent),
 {
     let device_path = find_touchscreen().ok_or("No touchscreen found")?;
     println!("Touch monitor using: {}", device_path);
 
     let file = File::open(&device_path)?;
-    let fd = file.as_raw_fd();
+    let (abs_x_max, abs_y_max) = read_touch_range(&file);
 
-    // Get screen dimensions from touchscreen
+    let mut decoder = TouchDecoder::new(abs_x_max, abs_y_max);
+    let mut buf = [0u8; INPUT_EVENT_SIZE];
+
+    loop {
+        let mut file_ref = &file;
+        if file_ref.read_exact(&mut buf).is_err() {
+            break;
+        }
+
+        let (ev_type, ev_code, ev_value) = parse_input_event(&buf);
+        decoder.feed(ev_type, ev_code, ev_value, &mut callback);
+    }
+
+    Ok(())
+}
+
+const INPUT_EVENT_SIZE: usize = 24; // sizeof(input_event)
+
+/// Get screen dimensions from touchscreen
+fn read_touch_range(file: &File) -> (i32, i32) {
+    let fd = file.as_raw_fd();
     let mut abs_x_max: i32 = 1080;
     let mut abs_y_max: i32 = 2340;
 
@@ -74,84 +176,168 @@ where
     }
 
     println!("Touch range: {}x{}", abs_x_max, abs_y_max);
+    (abs_x_max, abs_y_max)
+}
 
-    // GTK uses logical coordinates with 2x scaling on HiDPI
-    // Touch device reports physical coords, divide by scale factor
-    let scale_factor = 2.0;
-    let screen_w = abs_x_max as f64 / scale_factor;
-    let screen_h = abs_y_max as f64 / scale_factor;
-    println!("Using screen dimensions: {}x{} (scale {})", screen_w, screen_h, scale_factor);
+/// Parse input_event struct into (type, code, value)
+fn parse_input_event(buf: &[u8]) -> (u16, u16, i32) {
+    let ev_type = u16::from_ne_bytes([buf[16], buf[17]]);
+    let ev_code = u16::from_ne_bytes([buf[18], buf[19]]);
+    let ev_value = i32::from_ne_bytes([buf[20], buf[21], buf[22], buf[23]]);
+    (ev_type, ev_code, ev_value)
+}
 
-    let mut current_slot = 0i32;
-    let mut slot_x: [i32; 10] = [0; 10];
-    let mut slot_y: [i32; 10] = [0; 10];
-    let mut slot_tracking: [i32; 10] = [-1; 10];
+// ============ IN-LOOP READING ============
 
-    let mut buf = [0u8; 24]; // sizeof(input_event)
+type SharedHandler = Rc<RefCell<dyn FnMut(TouchEvent)>>;
 
-    loop {
-        use std::io::Read;
-        let mut file_ref = &file;
-        if file_ref.read_exact(&mut buf).is_err() {
-            break;
+/// Delay before retrying a touchscreen that failed or went away
+const REOPEN_DELAY: Duration = Duration::from_secs(1);
+
+/// Open the touchscreen non-blocking and parse it from a glib fd source.
+/// On any read error the source removes itself (closing the device) and
+/// the device is reopened after REOPEN_DELAY.
+fn attach_device_source(handler: SharedHandler) {
+    let Some(device_path) = find_touchscreen() else {
+        eprintln!("No touchscreen found");
+        return;
+    };
+
+    let file = match OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(&device_path)
+    {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Cannot open {}: {}", device_path, e);
+            schedule_reopen(handler);
+            return;
         }
+    };
+    println!("Touch monitor using: {} (main loop)", device_path);
 
-        // Parse input_event struct
-        let ev_type = u16::from_ne_bytes([buf[16], buf[17]]);
-        let ev_code = u16::from_ne_bytes([buf[18], buf[19]]);
-        let ev_value = i32::from_ne_bytes([buf[20], buf[21], buf[22], buf[23]]);
-
-        const EV_ABS: u16 = 3;
-        const ABS_MT_SLOT: u16 = 0x2f;
-        const ABS_MT_TRACKING_ID: u16 = 0x39;
-        const ABS_MT_POSITION_X: u16 = 0x35;
-        const ABS_MT_POSITION_Y: u16 = 0x36;
-
-        if ev_type == EV_ABS {
-            match ev_code {
-                ABS_MT_SLOT => {
-                    current_slot = ev_value.min(9).max(0);
-                }
-                ABS_MT_TRACKING_ID => {
-                    let slot = current_slot as usize;
-                    if ev_value == -1 {
-                        // Touch ended
-                        if slot_tracking[slot] != -1 {
-                            let x = (slot_x[slot] as f64 / abs_x_max as f64) * screen_w;
-                            let y = (slot_y[slot] as f64 / abs_y_max as f64) * screen_h;
-                            callback(TouchEvent::End(x, y));
-                            slot_tracking[slot] = -1;
-                        }
-                    } else {
-                        // Touch started
-                        slot_tracking[slot] = ev_value;
-                        let x = (slot_x[slot] as f64 / abs_x_max as f64) * screen_w;
-                        let y = (slot_y[slot] as f64 / abs_y_max as f64) * screen_h;
-                        callback(TouchEvent::Start(x, y));
+    let (abs_x_max, abs_y_max) = read_touch_range(&file);
+    let mut decoder = TouchDecoder::new(abs_x_max, abs_y_max);
+    let condition = glib::IOCondition::IN | glib::IOCondition::ERR | glib::IOCondition::HUP;
+
+    glib::unix_fd_add_local(file.as_raw_fd(), condition, move |_, _| {
+        let mut buf = [0u8; INPUT_EVENT_SIZE * 64];
+        loop {
+            match (&file).read(&mut buf) {
+                Ok(n) if n > 0 => {
+                    let mut handler = handler.borrow_mut();
+                    for event in buf[..n].chunks_exact(INPUT_EVENT_SIZE) {
+                        let (ev_type, ev_code, ev_value) = parse_input_event(event);
+                        decoder.feed(ev_type, ev_code, ev_value, &mut |e| (*handler)(e));
                     }
                 }
-                ABS_MT_POSITION_X => {
-                    let slot = current_slot as usize;
-                    slot_x[slot] = ev_value;
-                    if slot_tracking[slot] != -1 {
-                        let x = (slot_x[slot] as f64 / abs_x_max as f64) * screen_w;
-                        let y = (slot_y[slot] as f64 / abs_y_max as f64) * screen_h;
-                        callback(TouchEvent::Move(x, y));
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return ControlFlow::Continue,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                result => {
+                    if let Err(e) = result {
+                        eprintln!("Touchscreen read failed: {}", e);
                     }
+                    schedule_reopen(handler.clone());
+                    return ControlFlow::Break;
                 }
-                ABS_MT_POSITION_Y => {
-                    let slot = current_slot as usize;
-                    slot_y[slot] = ev_value;
-                    if slot_tracking[slot] != -1 {
-                        let x = (slot_x[slot] as f64 / abs_x_max as f64) * screen_w;
-                        let y = (slot_y[slot] as f64 / abs_y_max as f64) * screen_h;
-                        callback(TouchEvent::Move(x, y));
+            }
+        }
+    });
+}
+
+fn schedule_reopen(handler: SharedHandler) {
+    glib::timeout_add_local_once(REOPEN_DELAY, move || attach_device_source(handler));
+}
+
+// ============ EVDEV DECODING ============
+
+pub const EV_ABS: u16 = 3;
+pub const ABS_MT_SLOT: u16 = 0x2f;
+pub const ABS_MT_TRACKING_ID: u16 = 0x39;
+pub const ABS_MT_POSITION_X: u16 = 0x35;
+pub const ABS_MT_POSITION_Y: u16 = 0x36;
+
+/// Multitouch (type B) slot state machine turning raw evdev events into
+/// TouchEvents. Shared by the live monitor and the recording importers.
+pub struct TouchDecoder {
+    abs_x_max: i32,
+    abs_y_max: i32,
+    screen_w: f64,
+    screen_h: f64,
+    current_slot: usize,
+    slot_x: [i32; 10],
+    slot_y: [i32; 10],
+    slot_tracking: [i32; 10],
+}
+
+impl TouchDecoder {
+    pub fn new(abs_x_max: i32, abs_y_max: i32) -> Self {
+        // GTK uses logical coordinates with 2x scaling on HiDPI
+        // Touch device reports physical coords, divide by scale factor
+        let scale_factor = 2.0;
+        let screen_w = abs_x_max as f64 / scale_factor;
+        let screen_h = abs_y_max as f64 / scale_factor;
+        println!("Using screen dimensions: {}x{} (scale {})", screen_w, screen_h, scale_factor);
+
+        Self {
+            abs_x_max: abs_x_max.max(1),
+            abs_y_max: abs_y_max.max(1),
+            screen_w,
+            screen_h,
+            current_slot: 0,
+            slot_x: [0; 10],
+            slot_y: [0; 10],
+            slot_tracking: [-1; 10],
+        }
+    }
+
+    fn position(&self, slot: usize) -> (f64, f64) {
+        let x = (self.slot_x[slot] as f64 / self.abs_x_max as f64) * self.screen_w;
+        let y = (self.slot_y[slot] as f64 / self.abs_y_max as f64) * self.screen_h;
+        (x, y)
+    }
+
+    pub fn feed<F>(&mut self, ev_type: u16, ev_code: u16, ev_value: i32, callback: &mut F)
+    where
+        F: FnMut(TouchEvent),
+    {
+        if ev_type != EV_ABS {
+            return;
+        }
+
+        let slot = self.current_slot;
+        match ev_code {
+            ABS_MT_SLOT => {
+                self.current_slot = ev_value.clamp(0, 9) as usize;
+            }
+            ABS_MT_TRACKING_ID => {
+                if ev_value == -1 {
+                    // Touch ended
+                    if self.slot_tracking[slot] != -1 {
+                        let (x, y) = self.position(slot);
+                        callback(TouchEvent::End(x, y));
+                        self.slot_tracking[slot] = -1;
                     }
+                } else {
+                    // Touch started
+                    self.slot_tracking[slot] = ev_value;
+                    let (x, y) = self.position(slot);
+                    callback(TouchEvent::Start(x, y));
                 }
-                _ => {}
             }
+            ABS_MT_POSITION_X | ABS_MT_POSITION_Y => {
+                if ev_code == ABS_MT_POSITION_X {
+                    self.slot_x[slot] = ev_value;
+                } else {
+                    self.slot_y[slot] = ev_value;
+                }
+                if self.slot_tracking[slot] != -1 {
+                    let (x, y) = self.position(slot);
+                    callback(TouchEvent::Move(x, y));
+                }
+            }
+            _ => {}
         }
     }
-
-    Ok(())
 }
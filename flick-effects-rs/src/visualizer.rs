@@ -0,0 +1,194 @@
+use gio::prelude::*;
+use gtk4::gdk_pixbuf::Pixbuf;
+use std::f64::consts::PI;
+
+use crate::audio::{AudioLevels, SPECTRUM_BANDS};
+use crate::bounds::Bounds;
+use crate::config::Visualizer;
+use crate::gl_renderer::Sprite;
+use crate::sprites::GLOW_SCALE;
+
+/// Seconds to fade in when playback starts and out when it stops
+const FADE_IN: f64 = 0.5;
+const FADE_OUT: f64 = 1.5;
+/// Bars jump up towards louder levels at this rate and sink at FALL per second
+const RISE: f64 = 20.0;
+const FALL: f64 = 1.5;
+/// Reduced motion: bars follow the music slowly in both directions
+const STILL_RATE: f64 = 1.0;
+const ALPHA: f64 = 0.6;
+/// Tallest bottom bar, as a fraction of the screen height
+const BOTTOM_HEIGHT: f64 = 0.15;
+/// Ring radius as a fraction of the screen's shorter side; bars reach out
+/// another RING_REACH of it
+const RING_RADIUS: f64 = 0.3;
+const RING_REACH: f64 = 0.5;
+const MAX_DOT: f64 = 8.0;
+/// Cover art is scaled down to this before picking its color
+const ART_SAMPLE_SIZE: i32 = 64;
+
+/// Spectrum of the music playing, drawn as bars of dots that fade in while
+/// a media player plays and out when it pauses
+pub struct Spectrum {
+    pub playing: bool,
+    pub color: (f64, f64, f64),
+    // Latest levels heard, which the bars follow
+    levels: [f64; SPECTRUM_BANDS],
+    bars: [f64; SPECTRUM_BANDS],
+    fade: f64,
+}
+
+impl Spectrum {
+    pub fn new() -> Self {
+        Self {
+            playing: false,
+            color: (1.0, 1.0, 1.0),
+            levels: [0.0; SPECTRUM_BANDS],
+            bars: [0.0; SPECTRUM_BANDS],
+            fade: 0.0,
+        }
+    }
+
+    /// Levels of the music playing now; default levels once it stops
+    pub fn hear(&mut self, levels: &AudioLevels) {
+        self.levels = levels.spectrum;
+    }
+
+    pub fn update(&mut self, dt: f64, still: bool) {
+        self.fade = if self.playing {
+            (self.fade + dt / FADE_IN).min(1.0)
+        } else {
+            (self.fade - dt / FADE_OUT).max(0.0)
+        };
+        for (bar, &level) in self.bars.iter_mut().zip(&self.levels) {
+            if still {
+                *bar += (level - *bar) * (dt * STILL_RATE).min(1.0);
+            } else if level > *bar {
+                *bar += (level - *bar) * (dt * RISE).min(1.0);
+            } else {
+                *bar = (*bar - dt * FALL).max(level);
+            }
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.fade > 0.0
+    }
+
+    /// Still animating: playing, or not yet faded out
+    pub fn is_animating(&self) -> bool {
+        self.playing || self.is_visible()
+    }
+
+    pub fn sprites(&self, style: Visualizer, width: f64, height: f64, out: &mut Vec<Sprite>) {
+        let (r, g, b) = self.color;
+        let alpha = ALPHA * self.fade;
+        match style {
+            Visualizer::Off => {}
+            Visualizer::Bottom => {
+                let spacing = width / SPECTRUM_BANDS as f64;
+                let dot = (spacing * 0.3).min(MAX_DOT);
+                for (i, &bar) in self.bars.iter().enumerate() {
+                    let x = (i as f64 + 0.5) * spacing;
+                    let top = bar * height * BOTTOM_HEIGHT;
+                    let mut rise = 0.0;
+                    while rise < top {
+                        // Dimmer towards the top of the bar
+                        let a = alpha * (1.0 - 0.5 * rise / (height * BOTTOM_HEIGHT));
+                        out.push(Sprite::new(x, height - dot - rise, dot, r, g, b, a));
+                        rise += dot * 1.5;
+                    }
+                }
+            }
+            Visualizer::Ring => {
+                let (cx, cy) = (width / 2.0, height / 2.0);
+                let radius = width.min(height) * RING_RADIUS;
+                let reach = radius * RING_REACH;
+                let dot = (radius * PI / (2.0 * SPECTRUM_BANDS as f64) * 0.4).min(MAX_DOT);
+                // Bass at the top, treble at the bottom, mirrored left and right
+                for (i, &bar) in self.bars.iter().enumerate() {
+                    let angle = (i as f64 + 0.5) / SPECTRUM_BANDS as f64 * PI;
+                    for side in [-1.0, 1.0] {
+                        let (dx, dy) = (side * angle.sin(), -angle.cos());
+                        let mut out_by = 0.0;
+                        while out_by <= bar * reach {
+                            let a = alpha * (1.0 - 0.5 * out_by / reach);
+                            let d = radius + out_by;
+                            out.push(Sprite::new(cx + dx * d, cy + dy * d, dot, r, g, b, a));
+                            out_by += dot * 1.5;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn bounds(&self, style: Visualizer, width: f64, height: f64) -> Bounds {
+        match style {
+            Visualizer::Off => Bounds::empty(),
+            Visualizer::Bottom => Bounds {
+                x0: 0.0,
+                y0: height * (1.0 - BOTTOM_HEIGHT) - MAX_DOT * (1.0 + GLOW_SCALE),
+                x1: width,
+                y1: height,
+            },
+            Visualizer::Ring => {
+                let radius = width.min(height) * RING_RADIUS;
+                Bounds::around(width / 2.0, height / 2.0, radius * (1.0 + RING_REACH) + MAX_DOT * (1.0 + GLOW_SCALE))
+            }
+        }
+    }
+}
+
+impl Default for Spectrum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The most prominent saturated color of the cover art at `url`,
+/// brightened to full value; None for grey or missing art
+pub async fn art_color(url: &str) -> Option<[f64; 3]> {
+    let stream = gio::File::for_uri(url).read_future(glib::Priority::DEFAULT).await.ok()?;
+    // Decodes on a worker thread
+    let pixbuf = Pixbuf::from_stream_at_scale_future(&stream, ART_SAMPLE_SIZE, ART_SAMPLE_SIZE, true)
+        .await
+        .ok()?;
+    let bytes = pixbuf.read_pixel_bytes();
+    let channels = pixbuf.n_channels() as usize;
+    let stride = pixbuf.rowstride() as usize;
+
+    // Chroma-weighted colors in twelve hue buckets; the heaviest one wins
+    let mut buckets = [(0.0, [0.0; 3]); 12];
+    for y in 0..pixbuf.height() as usize {
+        for x in 0..pixbuf.width() as usize {
+            let p = &bytes[y * stride + x * channels..];
+            let [r, g, b] = [p[0], p[1], p[2]].map(|c| c as f64 / 255.0);
+            let max = r.max(g).max(b);
+            let chroma = max - r.min(g).min(b);
+            if chroma < 0.1 {
+                continue;
+            }
+            let hue = if max == r {
+                ((g - b) / chroma).rem_euclid(6.0)
+            } else if max == g {
+                (b - r) / chroma + 2.0
+            } else {
+                (r - g) / chroma + 4.0
+            };
+            let bucket = &mut buckets[((hue * 2.0) as usize).min(11)];
+            bucket.0 += chroma;
+            for (sum, c) in bucket.1.iter_mut().zip([r, g, b]) {
+                *sum += c * chroma;
+            }
+        }
+    }
+
+    let (weight, sum) = buckets.into_iter().max_by(|a, b| a.0.total_cmp(&b.0))?;
+    if weight <= 0.0 {
+        return None;
+    }
+    let color = sum.map(|c| c / weight);
+    let max = color[0].max(color[1]).max(color[2]);
+    Some(color.map(|c| c / max))
+}
@@ -0,0 +1,78 @@
+//! AudioAnalyzer on real capture: plays a low and a high tone into the
+//! default sink with pacat and checks what the analyzer hears on its
+//! monitor. Needs PulseAudio or PipeWire; set a null sink as the default
+//! (see the README) to run it silently:
+//!
+//!     cargo test --test audio -- --ignored
+
+use flick_effects::audio::{AudioAnalyzer, AudioLevels};
+use std::cell::{Cell, RefCell};
+use std::f64::consts::PI;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 22050;
+const SECONDS: f64 = 2.0;
+
+/// Start playing a sine at `hz` for SECONDS into the default sink
+fn play_tone(hz: f64) -> Child {
+    let mut child = Command::new("pacat")
+        .args(["--playback", "--raw", "--format=s16le", "--channels=1"])
+        .arg(format!("--rate={}", SAMPLE_RATE))
+        .stdin(Stdio::piped())
+        .spawn()
+        .expect("pacat runs");
+    let count = (SECONDS * SAMPLE_RATE as f64) as usize;
+    let mut pcm = Vec::with_capacity(count * 2);
+    for i in 0..count {
+        let sample = 0.5 * (2.0 * PI * hz * i as f64 / SAMPLE_RATE as f64).sin();
+        pcm.extend_from_slice(&((sample * 32767.0) as i16).to_le_bytes());
+    }
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // pacat plays in real time, so feed it from another thread
+    thread::spawn(move || {
+        stdin.write_all(&pcm).ok();
+    });
+    child
+}
+
+#[test]
+#[ignore = "plays tones through the default sink"]
+fn hears_tones_in_their_bands() {
+    // The analyzer reports on the default main context
+    glib::MainContext::default().block_on(async {
+        let heard = Rc::new(RefCell::new(Vec::<AudioLevels>::new()));
+        let ended = Rc::new(Cell::new(false));
+        let frames = heard.clone();
+        let stopped = ended.clone();
+        let _analyzer = AudioAnalyzer::new(
+            move |levels| frames.borrow_mut().push(levels),
+            move || stopped.set(true),
+        )
+        .expect("parec runs");
+
+        for hz in [100.0, 5000.0] {
+            let mut tone = play_tone(hz);
+            // Skip the start of the tone, then listen to half of it
+            glib::timeout_future(Duration::from_secs_f64(SECONDS / 4.0)).await;
+            heard.borrow_mut().clear();
+            glib::timeout_future(Duration::from_secs_f64(SECONDS / 2.0)).await;
+            let levels = std::mem::take(&mut *heard.borrow_mut());
+            tone.wait().expect("pacat finishes");
+
+            assert!(!ended.get(), "parec stopped");
+            assert!(!levels.is_empty(), "nothing heard at {} Hz", hz);
+            let count = levels.len() as f64;
+            let mean = |f: fn(&AudioLevels) -> f64| levels.iter().map(f).sum::<f64>() / count;
+            let (bass, treble) = (mean(|l| l.bass), mean(|l| l.treble));
+            if hz < 250.0 {
+                assert!(bass > treble, "{} Hz: bass {:.2} treble {:.2}", hz, bass, treble);
+            } else {
+                assert!(treble > bass, "{} Hz: bass {:.2} treble {:.2}", hz, bass, treble);
+            }
+        }
+    });
+}
@@ -0,0 +1,67 @@
+//! CallWatcher against the mock ModemManager (examples/mock-modemmanager.rs)
+//! on a private bus standing in for the system bus
+
+mod common;
+
+use flick_effects::calls::CallWatcher;
+use std::cell::RefCell;
+use std::process::{Child, Command};
+use std::rc::Rc;
+
+use common::{example, wait_for, PrivateBus};
+
+fn mock_modem(script: &str) -> Child {
+    Command::new(example("mock-modemmanager"))
+        .args(script.split(' '))
+        .spawn()
+        .expect("mock-modemmanager is built by cargo test")
+}
+
+/// Make sure the watcher's match rules are in place before a mock starts
+async fn sync_with_bus() {
+    let connection = gio::bus_get_future(gio::BusType::System).await.expect("system bus");
+    connection
+        .call_future(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "GetId",
+            None,
+            None,
+            gio::DBusCallFlags::NONE,
+            5000,
+        )
+        .await
+        .expect("bus round trip");
+}
+
+// One test, as the process only ever has one system bus connection
+#[test]
+fn ringing_follows_modem_manager() {
+    let bus = PrivateBus::start();
+    bus.use_as_system_bus();
+
+    glib::MainContext::new().block_on(async {
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let seen = reports.clone();
+        let watcher = CallWatcher::new(move |ringing| seen.borrow_mut().push(ringing)).expect("watch calls");
+        sync_with_bus().await;
+
+        // Answered, hung up while active, then a missed call deleted
+        // straight from ringing
+        let mut mock = mock_modem("ring 0.5 answer 0.5 hangup 0.5 ring 0.5 miss");
+        assert!(wait_for(15.0, || reports.borrow().len() >= 4).await, "reports: {:?}", reports.borrow());
+        mock.wait().ok();
+        assert_eq!(*reports.borrow(), vec![true, false, true, false]);
+        assert!(!watcher.is_ringing());
+
+        // ModemManager going away mid-ring takes its calls with it
+        reports.borrow_mut().clear();
+        let mut mock = mock_modem("ring 60");
+        assert!(wait_for(15.0, || watcher.is_ringing()).await, "mock call never rang");
+        mock.kill().ok();
+        mock.wait().ok();
+        assert!(wait_for(15.0, || !watcher.is_ringing()).await, "still ringing after ModemManager left");
+        assert_eq!(*reports.borrow(), vec![true, false]);
+    });
+}
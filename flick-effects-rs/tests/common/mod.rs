@@ -0,0 +1,79 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// A dbus-daemon of the test's own, stopped on drop
+pub struct PrivateBus {
+    daemon: Child,
+    pub address: String,
+}
+
+impl PrivateBus {
+    pub fn start() -> Self {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("dbus-daemon is needed for the D-Bus tests");
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().expect("stdout is piped"))
+            .read_line(&mut address)
+            .expect("dbus-daemon prints its address");
+        Self { daemon, address: address.trim().to_string() }
+    }
+
+    /// Make this the session bus for the rest of the test process, and for
+    /// the processes it starts. Only works before anything else in the
+    /// process connects to the session bus.
+    pub fn use_as_session_bus(&self) {
+        std::env::set_var("DBUS_SESSION_BUS_ADDRESS", &self.address);
+    }
+
+    /// The same, for the system bus
+    pub fn use_as_system_bus(&self) {
+        std::env::set_var("DBUS_SYSTEM_BUS_ADDRESS", &self.address);
+    }
+
+    /// A new connection of our own
+    pub fn connect(&self) -> gio::DBusConnection {
+        gio::DBusConnection::for_address_sync(
+            &self.address,
+            gio::DBusConnectionFlags::AUTHENTICATION_CLIENT | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+            None,
+            gio::Cancellable::NONE,
+        )
+        .expect("connect to the private bus")
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        self.daemon.kill().ok();
+        self.daemon.wait().ok();
+    }
+}
+
+/// Keep the main context running until `done` holds; false if it still
+/// doesn't after `seconds`
+pub async fn wait_for(seconds: f64, done: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs_f64(seconds);
+    while !done() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        glib::timeout_future(Duration::from_millis(10)).await;
+    }
+    true
+}
+
+/// An example program, which `cargo test` builds next to the test binaries
+pub fn example(name: &str) -> PathBuf {
+    let exe = std::env::current_exe().expect("test binary path");
+    // target/<profile>/deps/<test> -> target/<profile>/examples/<name>
+    let profile = exe.parent().and_then(|deps| deps.parent()).expect("test binary in target/<profile>/deps");
+    profile.join("examples").join(name)
+}
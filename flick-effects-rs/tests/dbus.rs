@@ -0,0 +1,254 @@
+//! The org.flick.Effects control interface, exported for a stand-in
+//! controller on a private bus
+
+mod common;
+
+use flick_effects::dbus::{self, ClientPolicy, Controller, PlayParams, Status, TriggerParams};
+use glib::{ToVariant, Variant};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use common::{wait_for, PrivateBus};
+
+const INVALID_ARGS: &str = "org.freedesktop.DBus.Error.InvalidArgs";
+const ACCESS_DENIED: &str = "org.freedesktop.DBus.Error.AccessDenied";
+const EFFECTS: [&str; 2] = ["fire", "ripple"];
+
+/// Keeps what it was asked to do
+struct FakeController {
+    touch_enabled: Cell<bool>,
+    touch_effect: RefCell<String>,
+    // Effect, x, y and duration of each trigger
+    triggered: RefCell<Vec<(String, f64, f64, f64)>>,
+    allowed: RefCell<Vec<String>>,
+}
+
+impl Controller for FakeController {
+    fn set_enabled(self: &Rc<Self>, feature: &str, enabled: bool) -> Result<(), String> {
+        match feature {
+            "touch" => {
+                self.touch_enabled.set(enabled);
+                Ok(())
+            }
+            _ => Err(format!("unknown feature '{}'", feature)),
+        }
+    }
+
+    fn set_effect(self: &Rc<Self>, name: &str) -> Result<(), String> {
+        if !EFFECTS.contains(&name) {
+            return Err(format!("unknown effect '{}'", name));
+        }
+        self.touch_effect.replace(name.to_string());
+        Ok(())
+    }
+
+    fn set_gradient(self: &Rc<Self>, target: &str, _gradient: &str) -> Result<(), String> {
+        Err(format!("unknown target '{}'", target))
+    }
+
+    fn reload_config(self: &Rc<Self>) {}
+
+    fn trigger_effect(self: &Rc<Self>, name: &str, x: f64, y: f64, params: &TriggerParams) -> Result<(), String> {
+        if !EFFECTS.contains(&name) {
+            return Err(format!("unknown effect '{}'", name));
+        }
+        self.triggered.borrow_mut().push((name.to_string(), x, y, params.duration));
+        Ok(())
+    }
+
+    fn play(self: &Rc<Self>, name: &str, _params: &PlayParams) -> Result<(), String> {
+        Err(format!("unknown effect '{}'", name))
+    }
+
+    fn client_policy(self: &Rc<Self>) -> ClientPolicy {
+        ClientPolicy { allowed: self.allowed.borrow().clone(), per_minute: 600.0 }
+    }
+
+    fn status(self: &Rc<Self>) -> Status {
+        Status {
+            touch_enabled: self.touch_enabled.get(),
+            touch_effect: self.touch_effect.borrow().clone(),
+            ambient_effects: Vec::new(),
+            living_pixels: false,
+            reduce_motion: false,
+            available_effects: EFFECTS.iter().map(|e| e.to_string()).collect(),
+            scripts: Vec::new(),
+            active_effects: self.triggered.borrow().len() as u32,
+            particles: 0,
+            quality: 1.0,
+        }
+    }
+}
+
+/// The interface exported on one connection and a client on another
+struct Service {
+    _bus: PrivateBus,
+    _service: gio::DBusConnection,
+    client: gio::DBusConnection,
+    name: String,
+    controller: Rc<FakeController>,
+}
+
+impl Service {
+    /// Must run on the test's main context, which then serves the interface
+    fn start() -> Self {
+        let bus = PrivateBus::start();
+        let service = bus.connect();
+        let controller = Rc::new(FakeController {
+            touch_enabled: Cell::new(true),
+            touch_effect: RefCell::new("fire".to_string()),
+            triggered: RefCell::new(Vec::new()),
+            allowed: RefCell::new(vec!["*".to_string()]),
+        });
+        dbus::export(&service, controller.clone()).expect("export the interface");
+        let name = service.unique_name().expect("unique name").to_string();
+        Self { client: bus.connect(), _bus: bus, _service: service, name, controller }
+    }
+
+    async fn call(&self, method: &str, params: Variant) -> Result<Variant, glib::Error> {
+        self.client
+            .call_future(
+                Some(&self.name),
+                dbus::OBJECT_PATH,
+                dbus::INTERFACE,
+                method,
+                Some(&params),
+                None,
+                gio::DBusCallFlags::NONE,
+                5000,
+            )
+            .await
+    }
+
+    async fn status(&self) -> HashMap<String, Variant> {
+        let reply = self.call("GetStatus", ().to_variant()).await.expect("GetStatus");
+        reply.get::<(HashMap<String, Variant>,)>().expect("a{sv} status").0
+    }
+}
+
+fn assert_error(result: Result<Variant, glib::Error>, name: &str) {
+    let error = result.expect_err("call should fail");
+    assert!(error.message().contains(name), "expected {}, got {}", name, error);
+}
+
+fn assert_invalid_args(result: Result<Variant, glib::Error>) {
+    assert_error(result, INVALID_ARGS);
+}
+
+fn no_params() -> HashMap<String, Variant> {
+    HashMap::new()
+}
+
+#[test]
+fn set_enabled_and_effect() {
+    glib::MainContext::new().block_on(async {
+        let service = Service::start();
+
+        service.call("SetEnabled", ("touch", false).to_variant()).await.expect("SetEnabled");
+        assert!(!service.controller.touch_enabled.get());
+        assert_invalid_args(service.call("SetEnabled", ("warp-drive", true).to_variant()).await);
+        assert_invalid_args(service.call("SetEnabled", ("touch",).to_variant()).await);
+
+        service.call("SetEffect", ("ripple",).to_variant()).await.expect("SetEffect");
+        assert_invalid_args(service.call("SetEffect", ("nonexistent",).to_variant()).await);
+
+        let status = service.status().await;
+        assert_eq!(status["touch_enabled"].get::<bool>(), Some(false));
+        assert_eq!(status["touch_effect"].get::<String>().as_deref(), Some("ripple"));
+        assert_eq!(status["available_effects"].get::<Vec<String>>(), Some(vec!["fire".to_string(), "ripple".to_string()]));
+    });
+}
+
+#[test]
+fn trigger_effect() {
+    glib::MainContext::new().block_on(async {
+        let service = Service::start();
+
+        let params = HashMap::from([("duration".to_string(), 1.5.to_variant())]);
+        service.call("TriggerEffect", ("ripple", 10.0, 20.0, params).to_variant()).await.expect("TriggerEffect");
+        service.call("TriggerEffect", ("fire", 1.0, 2.0, no_params()).to_variant()).await.expect("TriggerEffect");
+        assert_eq!(
+            *service.controller.triggered.borrow(),
+            vec![("ripple".to_string(), 10.0, 20.0, 1.5), ("fire".to_string(), 1.0, 2.0, 0.5)]
+        );
+
+        assert_invalid_args(service.call("TriggerEffect", ("nonexistent", 0.0, 0.0, no_params()).to_variant()).await);
+        let too_long = HashMap::from([("duration".to_string(), 60.0.to_variant())]);
+        assert_invalid_args(service.call("TriggerEffect", ("fire", 0.0, 0.0, too_long).to_variant()).await);
+        let unknown = HashMap::from([("speed".to_string(), 2.0.to_variant())]);
+        assert_invalid_args(service.call("TriggerEffect", ("fire", 0.0, 0.0, unknown).to_variant()).await);
+        assert_invalid_args(service.call("TriggerEffect", ("fire", 0.0, 0.0).to_variant()).await);
+        assert_eq!(service.controller.triggered.borrow().len(), 2);
+    });
+}
+
+#[test]
+fn effect_requests_from_listed_clients_only() {
+    glib::MainContext::new().block_on(async {
+        let service = Service::start();
+        let trigger = || ("fire", 0.0, 0.0, no_params()).to_variant();
+
+        service.controller.allowed.replace(Vec::new());
+        assert_error(service.call("TriggerEffect", trigger()).await, ACCESS_DENIED);
+        // Settings are not effect requests
+        service.call("SetEffect", ("ripple",).to_variant()).await.expect("SetEffect");
+
+        // Clients go by executable name, here the test binary's
+        let exe = std::env::current_exe().expect("test binary path");
+        let name = exe.file_name().expect("file name").to_string_lossy().to_string();
+        service.controller.allowed.replace(vec![name]);
+        service.call("TriggerEffect", trigger()).await.expect("TriggerEffect from a listed client");
+        assert_eq!(service.controller.triggered.borrow().len(), 1);
+    });
+}
+
+#[test]
+fn properties_changed_on_set_enabled() {
+    glib::MainContext::new().block_on(async {
+        let service = Service::start();
+
+        let changes = Rc::new(RefCell::new(Vec::<HashMap<String, Variant>>::new()));
+        let seen = changes.clone();
+        service.client.signal_subscribe(
+            Some(&service.name),
+            Some("org.freedesktop.DBus.Properties"),
+            Some("PropertiesChanged"),
+            Some(dbus::OBJECT_PATH),
+            Some(dbus::INTERFACE),
+            gio::DBusSignalFlags::NONE,
+            move |_, _, _, _, _, params| {
+                if let Some((_, changed, _)) = params.get::<(String, HashMap<String, Variant>, Vec<String>)>() {
+                    seen.borrow_mut().push(changed);
+                }
+            },
+        );
+
+        // Effect requests don't change properties
+        service.call("TriggerEffect", ("fire", 0.0, 0.0, no_params()).to_variant()).await.expect("TriggerEffect");
+        service.call("SetEnabled", ("touch", false).to_variant()).await.expect("SetEnabled");
+
+        let touch_disabled = || {
+            changes.borrow().iter().any(|c| c.get("TouchEnabled").and_then(|v| v.get::<bool>()) == Some(false))
+        };
+        assert!(wait_for(5.0, touch_disabled).await, "no PropertiesChanged with TouchEnabled false");
+        assert_eq!(changes.borrow().len(), 1);
+
+        // Properties read back through org.freedesktop.DBus.Properties
+        let reply = service
+            .client
+            .call_future(
+                Some(&service.name),
+                dbus::OBJECT_PATH,
+                "org.freedesktop.DBus.Properties",
+                "Get",
+                Some(&(dbus::INTERFACE, "TouchEnabled").to_variant()),
+                None,
+                gio::DBusCallFlags::NONE,
+                5000,
+            )
+            .await
+            .expect("Get TouchEnabled");
+        assert_eq!(reply.get::<(Variant,)>().and_then(|(v,)| v.get::<bool>()), Some(false));
+    });
+}
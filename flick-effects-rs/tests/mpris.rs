@@ -0,0 +1,73 @@
+//! MprisWatcher against the mock player (examples/mock-mpris.rs) on a
+//! private bus standing in for the session bus
+
+mod common;
+
+use flick_effects::mpris::{MprisWatcher, NowPlaying};
+use std::cell::RefCell;
+use std::process::{Child, Command};
+use std::rc::Rc;
+
+use common::{example, wait_for, PrivateBus};
+
+const ART: &str = "file:///tmp/flick-mock-cover.png";
+
+fn mock_player(script: &str) -> Child {
+    Command::new(example("mock-mpris"))
+        .args(script.split(' '))
+        .spawn()
+        .expect("mock-mpris is built by cargo test")
+}
+
+/// Make sure the watcher's match rules are in place before a mock starts
+async fn sync_with_bus() {
+    let connection = gio::bus_get_future(gio::BusType::Session).await.expect("session bus");
+    connection
+        .call_future(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "GetId",
+            None,
+            None,
+            gio::DBusCallFlags::NONE,
+            5000,
+        )
+        .await
+        .expect("bus round trip");
+}
+
+fn now(playing: bool, art_url: Option<&str>) -> NowPlaying {
+    NowPlaying { playing, art_url: art_url.map(String::from) }
+}
+
+// One test, as the process only ever has one session bus connection
+#[test]
+fn now_playing_follows_the_player() {
+    let bus = PrivateBus::start();
+    bus.use_as_session_bus();
+
+    glib::MainContext::new().block_on(async {
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let seen = reports.clone();
+        let _watcher = MprisWatcher::new(move |now: &NowPlaying| seen.borrow_mut().push(now.clone()))
+            .expect("watch media players");
+        sync_with_bus().await;
+
+        // New art while playing, a pause, playing again, then the player
+        // quits without stopping
+        let mut mock = mock_player(&format!("play 0.5 art {} pause 0.5 play 0.5 quit", ART));
+        assert!(wait_for(15.0, || reports.borrow().len() >= 5).await, "reports: {:?}", reports.borrow());
+        mock.wait().ok();
+        assert_eq!(
+            *reports.borrow(),
+            vec![
+                now(true, None),
+                now(true, Some(ART)),
+                now(false, None),
+                now(true, Some(ART)),
+                now(false, None),
+            ]
+        );
+    });
+}